@@ -3,7 +3,7 @@ use std::path::Path;
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
 use tempfile::{tempdir, TempDir};
 
-use librojo::cli::BuildCommand;
+use librojo::cli::{BuildCommand, ColorChoice, GlobalOptions, ProjectOptions};
 
 pub fn benchmark_small_place(c: &mut Criterion) {
     bench_build_place(c, "Small Place", "test-projects/benchmark_small_place")
@@ -20,7 +20,14 @@ fn bench_build_place(c: &mut Criterion, name: &str, path: &str) {
     group.bench_function("build", |b| {
         b.iter_batched(
             || place_setup(path),
-            |(_dir, options)| options.run().unwrap(),
+            |(_dir, options)| {
+                options
+                    .run(GlobalOptions {
+                        verbosity: 0,
+                        color: ColorChoice::Never,
+                    })
+                    .unwrap()
+            },
             BatchSize::SmallInput,
         )
     });
@@ -36,7 +43,17 @@ fn place_setup<P: AsRef<Path>>(input_path: P) -> (TempDir, BuildCommand) {
     let options = BuildCommand {
         project: input,
         watch: false,
-        output,
+        output: Some(output),
+        targets: Vec::new(),
+        deterministic: false,
+        base: None,
+        subtree: None,
+        check: false,
+        stamp: false,
+        stamp_version: None,
+        incremental: false,
+        manifest: None,
+        project_options: ProjectOptions::default(),
     };
 
     (dir, options)
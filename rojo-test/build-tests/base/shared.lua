@@ -0,0 +1 @@
+-- Added to the base place
\ No newline at end of file
@@ -0,0 +1 @@
+-- Listed in the build manifest
\ No newline at end of file
@@ -0,0 +1 @@
+-- Only on the server
\ No newline at end of file
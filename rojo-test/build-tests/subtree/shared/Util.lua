@@ -0,0 +1 @@
+-- Shared between the client and server
\ No newline at end of file
@@ -0,0 +1,3 @@
+return {
+	answer = 42,
+}
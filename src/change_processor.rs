@@ -10,6 +10,7 @@ use memofs::{IoResultExt, Vfs, VfsEvent};
 use rbx_dom_weak::types::{Ref, Variant};
 
 use crate::{
+    hooks::PreBuildHooks,
     message_queue::MessageQueue,
     snapshot::{
         apply_patch_set, compute_patch_set, AppliedPatchSet, InstigatingSource, PatchSet, RojoTree,
@@ -46,12 +47,14 @@ pub struct ChangeProcessor {
 
 impl ChangeProcessor {
     /// Spin up the ChangeProcessor, connecting it to the given tree, VFS, and
-    /// outbound message queue.
+    /// outbound message queue. The project's pre-build hooks are run again
+    /// whenever one of their inputs changes.
     pub fn start(
         tree: Arc<Mutex<RojoTree>>,
         vfs: Arc<Vfs>,
         message_queue: Arc<MessageQueue<AppliedPatchSet>>,
         tree_mutation_receiver: Receiver<PatchSet>,
+        pre_build_hooks: PreBuildHooks,
     ) -> Self {
         let (shutdown_sender, shutdown_receiver) = crossbeam_channel::bounded(1);
         let vfs_receiver = vfs.event_receiver();
@@ -59,6 +62,7 @@ impl ChangeProcessor {
             tree,
             vfs,
             message_queue,
+            pre_build_hooks,
         };
 
         let job_thread = jod_thread::Builder::new()
@@ -112,6 +116,10 @@ struct JobThreadContext {
     /// Whenever changes are applied to the DOM, we should push those changes
     /// into this message queue to inform any connected clients.
     message_queue: Arc<MessageQueue<AppliedPatchSet>>,
+
+    /// The project's pre-build hooks, which need to run again when their
+    /// inputs change.
+    pre_build_hooks: PreBuildHooks,
 }
 
 impl JobThreadContext {
@@ -123,6 +131,17 @@ impl JobThreadContext {
             .commit_event(&event)
             .expect("Error applying VFS change");
 
+        // Hooks run before the tree is updated. Any files they generate will
+        // raise their own events, which are handled after this one. If a hook
+        // fails, the tree is left as it was instead of being built from stale
+        // generated files.
+        if let VfsEvent::Create(path) | VfsEvent::Write(path) | VfsEvent::Remove(path) = &event {
+            if let Err(err) = self.pre_build_hooks.run_affected(path) {
+                log::error!("{:?}", err);
+                return;
+            }
+        }
+
         // For a given VFS event, we might have many changes to different parts
         // of the tree. Calculate and apply all of these changes.
         let applied_patches = match event {
@@ -359,3 +378,70 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
 
     Some(applied_patch_set)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::{glob::Glob, project::PreBuildHook, snapshot::InstanceContext};
+
+    /// Changes a file whose change runs a hook with the given command,
+    /// returning the resulting value of the instance made from that file and
+    /// how many messages were sent.
+    fn change_hook_input(hook_command: &str) -> (Variant, u32) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schema.txt");
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(&path, VfsSnapshot::file("before"))
+            .unwrap();
+
+        let vfs = Arc::new(Vfs::new(imfs.clone()));
+        let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, &path)
+            .unwrap()
+            .unwrap();
+
+        let context = JobThreadContext {
+            tree: Arc::new(Mutex::new(RojoTree::new(snapshot))),
+            vfs,
+            message_queue: Arc::new(MessageQueue::new()),
+            pre_build_hooks: PreBuildHooks::new(
+                vec![PreBuildHook {
+                    command: hook_command.to_owned(),
+                    inputs: vec![Glob::new("*.txt").unwrap()],
+                }],
+                dir.path().to_path_buf(),
+            ),
+        };
+
+        imfs.load_snapshot(&path, VfsSnapshot::file("after"))
+            .unwrap();
+        context.handle_vfs_event(VfsEvent::Write(path));
+
+        let tree = context.tree.lock().unwrap();
+        let root = tree.get_instance(tree.get_root_id()).unwrap();
+
+        (
+            root.properties()["Value"].clone(),
+            context.message_queue.cursor(),
+        )
+    }
+
+    #[test]
+    fn applies_changes_after_hooks_succeed() {
+        let (value, message_count) = change_hook_input("exit 0");
+
+        assert_eq!(value, Variant::String("after".to_owned()));
+        assert_eq!(message_count, 1);
+    }
+
+    #[test]
+    fn skips_changes_when_a_hook_fails() {
+        let (value, message_count) = change_hook_input("exit 1");
+
+        assert_eq!(value, Variant::String("before".to_owned()));
+        assert_eq!(message_count, 0);
+    }
+}
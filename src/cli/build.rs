@@ -1,21 +1,46 @@
 use std::{
-    io::{BufWriter, Write},
+    collections::{HashMap, HashSet, VecDeque},
+    ffi::OsStr,
+    io::{BufReader, BufWriter, Write},
     mem::forget,
+    ops::Range,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
-use anyhow::Context;
+use anyhow::{bail, format_err, Context};
 use clap::Parser;
 use fs_err::File;
 use memofs::Vfs;
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, InstanceBuilder, WeakDom,
+};
 use tokio::runtime::Runtime;
 
-use crate::serve_session::ServeSession;
+use crate::{
+    hooks::run_hook,
+    manifest::BuildManifest,
+    project::{BuildStamp, BuildTarget},
+    serve_session::ServeSession,
+    snapshot::{
+        apply_patch_set, compute_patch_set, InstanceContext, InstanceSnapshot, InstigatingSource,
+        PathIgnoreRule, RojoTree,
+    },
+    snapshot_middleware::{snapshot_from_vfs, JsonModel},
+    stamp::{hash_relevant_paths, remove_stamp, BuildInfo},
+};
 
-use super::resolve_path;
+use super::{
+    diff::{diff_rojo_trees, diff_trees, print_diff, tree_from_snapshot},
+    resolve_path, GlobalOptions, ProjectOptions,
+};
 
-const UNKNOWN_OUTPUT_KIND_ERR: &str = "Could not detect what kind of file to build. \
-                                       Expected output file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
+const UNKNOWN_INPUT_KIND_ERR: &str = "Could not detect what kind of file to read. \
+                                      Expected input file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
+
+pub(super) const UNKNOWN_OUTPUT_KIND_ERR: &str = "Could not detect what kind of file to build. \
+                                       Expected output file to end in .rbxl, .rbxlx, .rbxm, .rbxmx, or .model.json.";
 
 /// Generates a model or place file from the Rojo project.
 #[derive(Debug, Parser)]
@@ -24,31 +49,102 @@ pub struct BuildCommand {
     #[clap(default_value = "")]
     pub project: PathBuf,
 
-    /// Where to output the result.
+    /// Where to output the result. If omitted, the build targets defined in the
+    /// project file are built instead.
     ///
-    /// Should end in .rbxm, .rbxl, .rbxmx, or .rbxlx.
-    #[clap(long, short)]
-    pub output: PathBuf,
+    /// Should end in .rbxm, .rbxl, .rbxmx, .rbxlx, or .model.json.
+    #[clap(long, short, conflicts_with = "targets")]
+    pub output: Option<PathBuf>,
+
+    /// The name of a build target defined in the project file to build. Can be
+    /// given more than once. If neither this nor --output is given, every
+    /// build target in the project is built.
+    #[clap(long = "target")]
+    pub targets: Vec<String>,
 
     /// Whether to automatically rebuild when any input files change.
     #[clap(long)]
     pub watch: bool,
+
+    /// Whether to make the output byte-for-byte identical every time the same
+    /// project is built, which is useful for caching and reviewing artifacts.
+    ///
+    /// In XML files, each instance's referent is also derived from its path,
+    /// so adding or removing an instance doesn't change any other referents.
+    #[clap(long)]
+    pub deterministic: bool,
+
+    /// A place file to merge the project into. Instances in the place that the
+    /// project doesn't manage are kept, following $ignoreUnknownInstances.
+    #[clap(long)]
+    pub base: Option<PathBuf>,
+
+    /// The dotted path of an instance to build instead of the whole project,
+    /// like ReplicatedStorage.Shared. Only that instance and its descendants
+    /// are written.
+    #[clap(long)]
+    pub subtree: Option<String>,
+
+    /// Instead of writing anything, check whether the existing output files
+    /// match what would be built. Exits with an error if any of them differ.
+    #[clap(long, conflicts_with = "watch")]
+    pub check: bool,
+
+    /// Whether to stamp the build time, git commit, and a hash of the
+    /// project's files onto the built instance as attributes. Projects can
+    /// turn this on with buildStamp.
+    #[clap(long)]
+    pub stamp: bool,
+
+    /// A version string to stamp onto the built instance as an attribute.
+    /// Implies --stamp.
+    #[clap(long)]
+    pub stamp_version: Option<String>,
+
+    /// Whether to skip writing outputs whose inputs haven't changed since they
+    /// were last built. A fingerprint of the inputs is stored next to each
+    /// output in a .rojo-fingerprint file.
+    #[clap(long, conflicts_with = "check")]
+    pub incremental: bool,
+
+    /// Where to write a JSON manifest listing every built instance, the files
+    /// it came from, and a hash of each of those files. Instances from --base
+    /// aren't listed.
+    #[clap(long, conflicts_with = "check")]
+    pub manifest: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
 }
 
 impl BuildCommand {
-    pub fn run(self) -> anyhow::Result<()> {
+    pub fn run(self, global: GlobalOptions) -> anyhow::Result<()> {
         let project_path = resolve_path(&self.project);
 
-        let output_kind = detect_output_kind(&self.output).context(UNKNOWN_OUTPUT_KIND_ERR)?;
+        if let Some(output) = &self.output {
+            detect_output_kind(output).context(UNKNOWN_OUTPUT_KIND_ERR)?;
+        }
 
         log::trace!("Constructing in-memory filesystem");
         let vfs = Vfs::new_default();
         vfs.set_watch_enabled(self.watch);
 
-        let session = ServeSession::new(vfs, &project_path)?;
+        let session = ServeSession::new_with_hooks(
+            vfs,
+            &project_path,
+            self.project_options.instance_context(),
+        )?;
         let mut cursor = session.message_queue().cursor();
 
-        write_model(&session, &self.output, output_kind)?;
+        let outputs = self.outputs(&session)?;
+
+        if self.check {
+            let result = check_outputs(&session, &outputs, global.color.into());
+            forget(session);
+            return result;
+        }
+
+        self.write_outputs(&session, &outputs)?;
 
         if self.watch {
             let rt = Runtime::new().unwrap();
@@ -58,7 +154,7 @@ impl BuildCommand {
                 let (new_cursor, _patch_set) = rt.block_on(receiver).unwrap();
                 cursor = new_cursor;
 
-                write_model(&session, &self.output, output_kind)?;
+                self.write_outputs(&session, &outputs)?;
             }
         }
 
@@ -68,11 +164,233 @@ impl BuildCommand {
 
         Ok(())
     }
+
+    fn write_outputs(&self, session: &ServeSession, outputs: &[BuildOutput]) -> anyhow::Result<()> {
+        let mut manifest = self
+            .manifest
+            .as_ref()
+            .map(|_| BuildManifest::new(session.root_dir()));
+
+        for output in outputs {
+            write_model(session, output, manifest.as_mut())?;
+        }
+
+        if let (Some(manifest), Some(manifest_path)) = (&manifest, &self.manifest) {
+            write_manifest(manifest, manifest_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Figures out which files this build should produce, either from the
+    /// command line or from the project's build targets.
+    fn outputs(&self, session: &ServeSession) -> anyhow::Result<Vec<BuildOutput>> {
+        let mut outputs = match &self.output {
+            Some(output) => {
+                let kind = detect_output_kind(output).context(UNKNOWN_OUTPUT_KIND_ERR)?;
+                vec![BuildOutput::new(output.clone(), kind)]
+            }
+            None => self.target_outputs(session)?,
+        };
+
+        let stamp = self.stamp_config(session);
+
+        for output in &mut outputs {
+            output.deterministic = self.deterministic;
+            output.incremental = self.incremental;
+            output.stamp = stamp.clone();
+
+            if let Some(subtree) = &self.subtree {
+                output.subtree = Some(subtree.clone());
+            }
+
+            if let Some(base) = &self.base {
+                if !output.kind.is_place() {
+                    bail!(
+                        "--base can only be used when building a place file, but {} is a model",
+                        output.path.display()
+                    );
+                }
+
+                output.base = Some(base.clone());
+            }
+
+            // Places are written as the children of their root, so a subtree
+            // would turn its children into services.
+            if output.subtree.is_some() && output.kind.is_place() {
+                bail!(
+                    "A subtree can only be built into a model file, but {} is a place",
+                    output.path.display()
+                );
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Combines the project's build stamp settings with the ones given on the
+    /// command line. Returns `None` if the build shouldn't be stamped.
+    fn stamp_config(&self, session: &ServeSession) -> Option<BuildStamp> {
+        let project_stamp = session.build_stamp().cloned();
+
+        if !self.stamp && self.stamp_version.is_none() {
+            return project_stamp;
+        }
+
+        let mut stamp = project_stamp.unwrap_or_default();
+
+        if let Some(version) = &self.stamp_version {
+            stamp.version = Some(version.clone());
+        }
+
+        Some(stamp)
+    }
+
+    fn target_outputs(&self, session: &ServeSession) -> anyhow::Result<Vec<BuildOutput>> {
+        let build_targets = session.build_targets();
+
+        if build_targets.is_empty() {
+            bail!(
+                "No output path was given, and project '{}' doesn't define any build targets.\n\
+                 Use --output to choose where to build the project.",
+                session.project_name()
+            );
+        }
+
+        if self.targets.is_empty() {
+            return build_targets
+                .values()
+                .map(|target| BuildOutput::from_target(target, session.root_dir()))
+                .collect();
+        }
+
+        self.targets
+            .iter()
+            .map(|name| {
+                let target = build_targets.get(name).with_context(|| {
+                    let names: Vec<&str> = build_targets.keys().map(String::as_str).collect();
+
+                    format!(
+                        "Project '{}' has no build target named '{}'. Available targets are: {}",
+                        session.project_name(),
+                        name,
+                        names.join(", ")
+                    )
+                })?;
+
+                BuildOutput::from_target(target, session.root_dir())
+            })
+            .collect()
+    }
+}
+
+/// A single file produced by a build.
+#[derive(Debug)]
+struct BuildOutput {
+    path: PathBuf,
+    kind: OutputKind,
+
+    /// The dotted path of the instance to build instead of the tree's root.
+    subtree: Option<String>,
+
+    /// Instances that came from a file rejected by one of these rules are left
+    /// out of the output.
+    ignore_rules: Vec<PathIgnoreRule>,
+
+    /// Whether to sort the tree before writing it so that the same inputs
+    /// always produce the same bytes.
+    deterministic: bool,
+
+    /// A place file to merge the tree into instead of writing it on its own.
+    base: Option<PathBuf>,
+
+    /// Where and how to stamp information about the build into the output.
+    stamp: Option<BuildStamp>,
+
+    /// Whether to skip writing the output if its inputs haven't changed since
+    /// it was last written.
+    incremental: bool,
+}
+
+impl BuildOutput {
+    fn new(path: PathBuf, kind: OutputKind) -> Self {
+        Self {
+            path,
+            kind,
+            subtree: None,
+            ignore_rules: Vec::new(),
+            deterministic: false,
+            base: None,
+            stamp: None,
+            incremental: false,
+        }
+    }
+
+    fn from_target(target: &BuildTarget, root_dir: &Path) -> anyhow::Result<Self> {
+        let path = root_dir.join(&target.output);
+
+        let kind = match &target.kind {
+            Some(kind) => kind.parse()?,
+            None => detect_output_kind(&path).with_context(|| {
+                format!(
+                    "{} Set the build target's kind to choose one.",
+                    UNKNOWN_OUTPUT_KIND_ERR
+                )
+            })?,
+        };
+
+        let ignore_rules = target
+            .glob_ignore_paths
+            .iter()
+            .map(|glob| PathIgnoreRule {
+                glob: glob.clone(),
+                base_path: root_dir.to_path_buf(),
+            })
+            .collect();
+
+        Ok(Self {
+            subtree: target.subtree.clone(),
+            ignore_rules,
+            ..Self::new(path, kind)
+        })
+    }
+
+    /// Tells whether the tree needs to be copied before being written, either
+    /// because this output contains only part of it, because it needs to be
+    /// sorted, or because it gets stamped.
+    fn needs_copy(&self) -> bool {
+        self.subtree.is_some()
+            || !self.ignore_rules.is_empty()
+            || self.deterministic
+            || self.stamp.is_some()
+    }
+
+    /// The path of the file that holds the fingerprint of the inputs this
+    /// output was last built from.
+    fn fingerprint_path(&self) -> PathBuf {
+        let mut file_name = self.path.file_name().unwrap_or_default().to_owned();
+        file_name.push(".rojo-fingerprint");
+
+        self.path.with_file_name(file_name)
+    }
+
+    fn includes(&self, tree: &RojoTree, id: Ref) -> bool {
+        let source = tree
+            .get_metadata(id)
+            .and_then(|metadata| metadata.instigating_source.as_ref());
+
+        match source {
+            Some(InstigatingSource::Path(path)) => {
+                self.ignore_rules.iter().all(|rule| rule.passes(path))
+            }
+            _ => true,
+        }
+    }
 }
 
 /// The different kinds of output that Rojo can build to.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum OutputKind {
+pub(super) enum OutputKind {
     /// An XML model file.
     Rbxmx,
 
@@ -84,9 +402,60 @@ enum OutputKind {
 
     /// A binary place file.
     Rbxl,
+
+    /// A JSON model file, in the same format that Rojo reads `.model.json`
+    /// files in.
+    JsonModel,
+}
+
+impl OutputKind {
+    /// Tells whether this kind of file holds a place instead of a model.
+    pub fn is_place(self) -> bool {
+        matches!(self, OutputKind::Rbxl | OutputKind::Rbxlx)
+    }
+
+    /// Tells whether this kind of file is written by rbx_xml.
+    pub fn is_xml(self) -> bool {
+        matches!(self, OutputKind::Rbxmx | OutputKind::Rbxlx)
+    }
+
+    /// The name of this kind, as accepted by `OutputKind::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputKind::Rbxmx => "rbxmx",
+            OutputKind::Rbxlx => "rbxlx",
+            OutputKind::Rbxm => "rbxm",
+            OutputKind::Rbxl => "rbxl",
+            OutputKind::JsonModel => "model.json",
+        }
+    }
+}
+
+impl FromStr for OutputKind {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "rbxmx" => Ok(OutputKind::Rbxmx),
+            "rbxlx" => Ok(OutputKind::Rbxlx),
+            "rbxm" => Ok(OutputKind::Rbxm),
+            "rbxl" => Ok(OutputKind::Rbxl),
+            "model.json" => Ok(OutputKind::JsonModel),
+            attempted => Err(format_err!(
+                "Invalid output kind '{}'. Valid kinds are: rbxm, rbxmx, rbxl, rbxlx, model.json",
+                attempted
+            )),
+        }
+    }
 }
 
-fn detect_output_kind(output: &Path) -> Option<OutputKind> {
+pub(super) fn detect_output_kind(output: &Path) -> Option<OutputKind> {
+    // `Path::extension` only sees the last part of a double extension.
+    let file_name = output.file_name()?.to_str()?;
+    if file_name.ends_with(".model.json") {
+        return Some(OutputKind::JsonModel);
+    }
+
     let extension = output.extension()?.to_str()?;
 
     match extension {
@@ -98,58 +467,733 @@ fn detect_output_kind(output: &Path) -> Option<OutputKind> {
     }
 }
 
+/// Reads a place or model file into a `WeakDom`.
+///
+/// The root of the returned DOM stands in for the DataModel: for places, its
+/// children are the place's services, and for models, they are the model's
+/// top-level instances.
+pub(super) fn read_model(input: &Path) -> anyhow::Result<WeakDom> {
+    let input_kind = detect_output_kind(input).context(UNKNOWN_INPUT_KIND_ERR)?;
+    let reader = BufReader::new(File::open(input)?);
+
+    let dom = match input_kind {
+        OutputKind::Rbxm | OutputKind::Rbxl => rbx_binary::from_reader(reader)
+            .with_context(|| format!("Malformed binary file: {}", input.display()))?,
+        OutputKind::Rbxmx | OutputKind::Rbxlx => {
+            let options = rbx_xml::DecodeOptions::new()
+                .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+
+            rbx_xml::from_reader(reader, options)
+                .with_context(|| format!("Malformed XML file: {}", input.display()))?
+        }
+        OutputKind::JsonModel => bail!(UNKNOWN_INPUT_KIND_ERR),
+    };
+
+    Ok(dom)
+}
+
 fn xml_encode_config() -> rbx_xml::EncodeOptions {
     rbx_xml::EncodeOptions::new().property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown)
 }
 
+/// Writes one output of the build, recording what was written in `manifest`
+/// if there is one.
 #[profiling::function]
 fn write_model(
     session: &ServeSession,
-    output: &Path,
-    output_kind: OutputKind,
+    output: &BuildOutput,
+    manifest: Option<&mut BuildManifest>,
 ) -> anyhow::Result<()> {
     println!("Building project '{}'", session.project_name());
 
-    let tree = session.tree();
-    let root_id = tree.get_root_id();
+    let session_tree = session.tree();
+
+    let fingerprint = if output.incremental {
+        let fingerprint = fingerprint(session, &session_tree, output)?;
+        let previous = fs_err::read_to_string(output.fingerprint_path()).ok();
+
+        if output.path.exists() && previous.as_deref() == Some(fingerprint.to_hex().as_str()) {
+            println!("{} is up to date", output.path.display());
+
+            // The manifest still lists outputs that didn't need to be written,
+            // as they would have been written.
+            if let Some(manifest) = manifest {
+                let merged_tree = merged_tree(&session_tree, output)?;
+                let tree = merged_tree.as_ref().unwrap_or(&session_tree);
+                let copy = output_dom(tree, output, None)?;
+
+                add_to_manifest(manifest, session, tree, output, copy.as_ref())?;
+            }
+
+            return Ok(());
+        }
+
+        Some(fingerprint)
+    } else {
+        None
+    };
+
+    let merged_tree = merged_tree(&session_tree, output)?;
+    let tree = merged_tree.as_ref().unwrap_or(&session_tree);
+
+    let build_info = match &output.stamp {
+        Some(stamp) => Some(BuildInfo::collect(
+            &session_tree,
+            session.vfs(),
+            session.root_dir(),
+            stamp.version.clone(),
+        )?),
+        None => None,
+    };
+
+    let copy = output_dom(tree, output, build_info.as_ref())?;
+    write_tree(tree, output, copy.as_ref().map(|(dom, _)| dom))?;
+
+    if let Some(manifest) = manifest {
+        add_to_manifest(manifest, session, tree, output, copy.as_ref())?;
+    }
+
+    let filename = output
+        .path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("<invalid utf-8>");
+    println!("Built project to {}", filename);
+
+    run_post_build_hooks(session, output)?;
+
+    // The fingerprint is only written once everything succeeded, so a failed
+    // build is always retried.
+    if let Some(fingerprint) = fingerprint {
+        fs_err::write(output.fingerprint_path(), fingerprint.to_hex().as_str())?;
+    }
+
+    Ok(())
+}
+
+/// Hashes everything that can change what gets written for `output`: the
+/// files the tree came from, the project file, the output's settings, and the
+/// version of Rojo doing the build.
+fn fingerprint(
+    session: &ServeSession,
+    tree: &RojoTree,
+    output: &BuildOutput,
+) -> anyhow::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+
+    let mut update_str = |value: &str| {
+        hasher.update(&(value.len() as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+    };
+
+    update_str(env!("CARGO_PKG_VERSION"));
+
+    // Variables and profiles from the command line change what's in project
+    // files without changing the files themselves.
+    let root_context = &tree.get_metadata(tree.get_root_id()).unwrap().context;
+    for (name, value) in root_context.variables.iter() {
+        update_str(name);
+        update_str(value);
+    }
+    update_str(root_context.profile.as_deref().unwrap_or_default());
+
+    update_str(output.kind.name());
+    update_str(output.subtree.as_deref().unwrap_or_default());
+    update_str(if output.deterministic {
+        "deterministic"
+    } else {
+        ""
+    });
+
+    for rule in &output.ignore_rules {
+        update_str(rule.glob.as_str());
+    }
+
+    if let Some(stamp) = &output.stamp {
+        update_str(stamp.instance.as_deref().unwrap_or_default());
+        update_str(stamp.version.as_deref().unwrap_or_default());
+    }
+
+    hasher.update(&fs_err::read(session.project_path())?);
+    hasher.update(hash_relevant_paths(tree, session.vfs(), session.root_dir())?.as_bytes());
+
+    if let Some(base) = &output.base {
+        hasher.update(&fs_err::read(base)?);
+    }
 
+    Ok(hasher.finalize())
+}
+
+/// Runs the project's post-build hooks for an output that was just written.
+fn run_post_build_hooks(session: &ServeSession, output: &BuildOutput) -> anyhow::Result<()> {
+    // Hooks run from the project's folder, so a relative output path wouldn't
+    // point at the output anymore.
+    let output_path = resolve_path(&output.path);
+
+    let envs = [
+        ("ROJO_OUTPUT_PATH", output_path.as_os_str()),
+        ("ROJO_OUTPUT_KIND", OsStr::new(output.kind.name())),
+    ];
+
+    for hook in session.post_build_hooks() {
+        run_hook(&hook.command, session.root_dir(), &envs)
+            .with_context(|| format!("A post-build hook failed for {}", output.path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Writes the file described by `output`, either from `copy`, the part of the
+/// tree that `output_dom` copied out for it, or from the whole tree if no copy
+/// was needed.
+fn write_tree(tree: &RojoTree, output: &BuildOutput, copy: Option<&WeakDom>) -> anyhow::Result<()> {
+    match copy {
+        Some(dom) if output.deterministic && output.kind.is_xml() => {
+            write_xml_with_path_referents(dom, output.kind, &output.path)
+        }
+        Some(dom) => write_dom(dom, dom.root_ref(), output.kind, &output.path),
+        None => write_dom(tree.inner(), tree.get_root_id(), output.kind, &output.path),
+    }
+}
+
+/// Records the instances written to `output` in the manifest. `tree` and
+/// `copy` should be the same ones that `write_tree` wrote the output from.
+fn add_to_manifest(
+    manifest: &mut BuildManifest,
+    session: &ServeSession,
+    tree: &RojoTree,
+    output: &BuildOutput,
+    copy: Option<&(WeakDom, HashMap<Ref, Ref>)>,
+) -> anyhow::Result<()> {
+    let kind = output.kind.name();
+
+    match copy {
+        Some((dom, copied_ids)) => {
+            let source_ids: HashMap<Ref, Ref> = copied_ids
+                .iter()
+                .map(|(&source_id, &copied_id)| (copied_id, source_id))
+                .collect();
+
+            manifest.add_output(
+                dom,
+                dom.root_ref(),
+                |id| tree.get_metadata(source_ids[&id]),
+                session.vfs(),
+                &output.path,
+                kind,
+            )
+        }
+        None => manifest.add_output(
+            tree.inner(),
+            tree.get_root_id(),
+            |id| tree.get_metadata(id),
+            session.vfs(),
+            &output.path,
+            kind,
+        ),
+    }
+}
+
+/// Returns the tree that should be written for `output` if it has a base
+/// place to be merged into.
+fn merged_tree(tree: &RojoTree, output: &BuildOutput) -> anyhow::Result<Option<RojoTree>> {
+    match &output.base {
+        Some(base) => Ok(Some(merge_into_base(tree, base)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the ID of the instance in the tree that `output` is built from.
+fn output_root(tree: &RojoTree, output: &BuildOutput) -> anyhow::Result<Ref> {
+    match &output.subtree {
+        Some(subtree) => tree
+            .find_by_full_name(subtree)
+            .with_context(|| format!("Could not find an instance named '{}' to build", subtree)),
+        None => Ok(tree.get_root_id()),
+    }
+}
+
+/// Writes a manifest describing where every instance in the build came from.
+fn write_manifest(manifest: &BuildManifest, path: &Path) -> anyhow::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, manifest)?;
+    writeln!(file)?;
+    file.flush()?;
+
+    println!("Wrote build manifest to {}", path.display());
+
+    Ok(())
+}
+
+/// Returns a copy of the part of the tree that `output` contains, or `None` if
+/// the tree can be written as-is. Along with the copy comes a map from IDs in
+/// the tree to IDs in the copy, like `copy_subtree` returns.
+///
+/// If `build_info` is given, it's stamped onto the instance chosen by the
+/// output's stamp settings.
+fn output_dom(
+    tree: &RojoTree,
+    output: &BuildOutput,
+    build_info: Option<&BuildInfo>,
+) -> anyhow::Result<Option<(WeakDom, HashMap<Ref, Ref>)>> {
+    if !output.needs_copy() {
+        return Ok(None);
+    }
+
+    let root_id = output_root(tree, output)?;
+
+    let (mut dom, copied_ids) = copy_subtree(tree, root_id, output.deterministic, |id| {
+        output.includes(tree, id)
+    });
+
+    if let (Some(build_info), Some(stamp)) = (build_info, &output.stamp) {
+        let stamp_id = match &stamp.instance {
+            Some(instance) => {
+                let id = tree.find_by_full_name(instance).with_context(|| {
+                    format!("Could not find an instance named '{}' to stamp", instance)
+                })?;
+
+                *copied_ids.get(&id).with_context(|| {
+                    format!(
+                        "Cannot stamp '{}' because it isn't part of {}",
+                        instance,
+                        output.path.display()
+                    )
+                })?
+            }
+            // Place files don't store the DataModel itself, so places are
+            // stamped on ReplicatedStorage, where any script can read it.
+            None if output.kind.is_place() => dom
+                .root()
+                .children()
+                .iter()
+                .copied()
+                .find(|&id| dom.get_by_ref(id).unwrap().class == "ReplicatedStorage")
+                .with_context(|| {
+                    format!(
+                        "Cannot stamp {} because it has no ReplicatedStorage. \
+                         Set buildStamp.instance to choose an instance to stamp.",
+                        output.path.display()
+                    )
+                })?,
+            None => dom.root_ref(),
+        };
+
+        let instance = dom.get_by_ref_mut(stamp_id).unwrap();
+        build_info.apply(&mut instance.properties);
+    }
+
+    Ok(Some((dom, copied_ids)))
+}
+
+/// Compares each output with the file that already exists at its path,
+/// printing any differences. Fails if any of them are out of date.
+fn check_outputs(
+    session: &ServeSession,
+    outputs: &[BuildOutput],
+    color: termcolor::ColorChoice,
+) -> anyhow::Result<()> {
+    let mut stale_count = 0;
+
+    for output in outputs {
+        let session_tree = session.tree();
+        let merged_tree = merged_tree(&session_tree, output)?;
+        let tree = merged_tree.as_ref().unwrap_or(&session_tree);
+
+        // Stamps change on every build, so they're left out of the comparison.
+        let built_tree = output_dom(tree, output, None)?.map(|(dom, _)| {
+            let root_id = dom.root_ref();
+            tree_from_snapshot(InstanceSnapshot::from_tree(dom, root_id))
+        });
+        let built_tree = built_tree.as_ref().unwrap_or(tree);
+
+        if !output.path.exists() {
+            println!("{} does not exist", output.path.display());
+            stale_count += 1;
+            continue;
+        }
+
+        let diff = match output.kind {
+            OutputKind::JsonModel => {
+                let mut existing_tree = read_json_model(&output.path)?;
+
+                if output.stamp.is_some() {
+                    let ids: Vec<Ref> = existing_tree
+                        .descendants(existing_tree.get_root_id())
+                        .map(|instance| instance.id())
+                        .collect();
+
+                    for id in ids {
+                        let mut instance = existing_tree.get_instance_mut(id).unwrap();
+                        remove_stamp(instance.properties_mut());
+                    }
+                }
+
+                diff_rojo_trees(built_tree, &existing_tree)
+            }
+            _ => {
+                let mut existing_dom = read_model(&output.path)?;
+
+                if output.stamp.is_some() {
+                    let mut to_visit = vec![existing_dom.root_ref()];
+
+                    while let Some(id) = to_visit.pop() {
+                        let instance = existing_dom.get_by_ref_mut(id).unwrap();
+                        remove_stamp(&mut instance.properties);
+                        to_visit.extend_from_slice(instance.children());
+                    }
+                }
+
+                diff_trees(built_tree, existing_dom)?
+            }
+        };
+
+        if diff.is_empty() {
+            println!("{} is up to date", output.path.display());
+        } else {
+            println!("{} is out of date:", output.path.display());
+            print_diff(&diff, color)?;
+            stale_count += 1;
+        }
+    }
+
+    if stale_count > 0 {
+        bail!(
+            "{} build output(s) are out of date. Run rojo build to update them.",
+            stale_count
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads a `.model.json` file into a tree the same way a project would.
+pub(super) fn read_json_model(path: &Path) -> anyhow::Result<RojoTree> {
+    let vfs = Vfs::new_default();
+    vfs.set_watch_enabled(false);
+
+    let snapshot = snapshot_from_vfs(&InstanceContext::default(), &vfs, path)?
+        .with_context(|| format!("Model file was empty: {}", path.display()))?;
+
+    Ok(RojoTree::new(snapshot))
+}
+
+/// Writes an instance and its descendants to a file of the given kind. For
+/// places, `root_id` should be the DataModel.
+pub(super) fn write_dom(
+    dom: &WeakDom,
+    root_id: Ref,
+    kind: OutputKind,
+    path: &Path,
+) -> anyhow::Result<()> {
     log::trace!("Opening output file for write");
-    let mut file = BufWriter::new(File::create(output)?);
+    let mut file = BufWriter::new(File::create(path)?);
 
-    match output_kind {
+    match kind {
         OutputKind::Rbxm => {
-            rbx_binary::to_writer(&mut file, tree.inner(), &[root_id])?;
+            rbx_binary::to_writer(&mut file, dom, &[root_id])?;
         }
         OutputKind::Rbxl => {
-            let root_instance = tree.get_instance(root_id).unwrap();
+            let root_instance = dom.get_by_ref(root_id).unwrap();
             let top_level_ids = root_instance.children();
 
-            rbx_binary::to_writer(&mut file, tree.inner(), top_level_ids)?;
+            rbx_binary::to_writer(&mut file, dom, top_level_ids)?;
         }
         OutputKind::Rbxmx => {
             // Model files include the root instance of the tree and all its
             // descendants.
 
-            rbx_xml::to_writer(&mut file, tree.inner(), &[root_id], xml_encode_config())?;
+            rbx_xml::to_writer(&mut file, dom, &[root_id], xml_encode_config())?;
         }
         OutputKind::Rbxlx => {
             // Place files don't contain an entry for the DataModel, but our
             // WeakDom representation does.
 
-            let root_instance = tree.get_instance(root_id).unwrap();
+            let root_instance = dom.get_by_ref(root_id).unwrap();
             let top_level_ids = root_instance.children();
 
-            rbx_xml::to_writer(&mut file, tree.inner(), top_level_ids, xml_encode_config())?;
+            rbx_xml::to_writer(&mut file, dom, top_level_ids, xml_encode_config())?;
+        }
+        OutputKind::JsonModel => {
+            // Like other models, JSON models contain the root instance and
+            // all its descendants. Its name comes from the file name instead.
+            let mut model = JsonModel::from_instance(dom, root_id);
+            model.name = None;
+
+            serde_json::to_writer_pretty(&mut file, &model)?;
+            writeln!(file)?;
         }
     }
 
     file.flush()?;
 
-    let filename = output
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("<invalid utf-8>");
-    println!("Built project to {}", filename);
+    Ok(())
+}
+
+/// Writes a dom to an XML model or place file like `write_dom`, but with each
+/// instance's referent derived from its path instead of the order instances
+/// are written in. Adding or removing an instance then leaves the referents of
+/// every other instance alone, which keeps diffs of built files small.
+///
+/// rbx_xml has no way to choose referents, so they're replaced in its output.
+fn write_xml_with_path_referents(
+    dom: &WeakDom,
+    kind: OutputKind,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let root_id = dom.root_ref();
+    let top_level_ids = if kind.is_place() {
+        dom.root().children().to_vec()
+    } else {
+        vec![root_id]
+    };
+
+    let mut xml = Vec::new();
+    rbx_xml::to_writer(&mut xml, dom, &top_level_ids, xml_encode_config())?;
+    let xml = String::from_utf8(xml).context("rbx_xml wrote a file that wasn't UTF-8")?;
+
+    let referents = path_referents(dom, &top_level_ids);
+    fs_err::write(path, replace_referents(&xml, &referents))?;
 
     Ok(())
 }
+
+/// Derives a referent for every instance under `top_level_ids` from the names
+/// of the instance and its ancestors. Each name is paired with its index among
+/// siblings of the same name, so that siblings that share a name still get
+/// different referents.
+///
+/// Referents are returned in the order rbx_xml writes instances in: each
+/// instance followed by its descendants.
+fn path_referents(dom: &WeakDom, top_level_ids: &[Ref]) -> Vec<String> {
+    fn visit(
+        dom: &WeakDom,
+        siblings: &[Ref],
+        parent_hash: Option<&blake3::Hash>,
+        referents: &mut Vec<String>,
+    ) {
+        let mut name_counts: HashMap<&str, u32> = HashMap::new();
+
+        for &id in siblings {
+            let instance = dom.get_by_ref(id).unwrap();
+            let index = name_counts.entry(instance.name.as_str()).or_default();
+
+            let mut hasher = blake3::Hasher::new();
+            if let Some(parent_hash) = parent_hash {
+                hasher.update(parent_hash.as_bytes());
+            }
+            hasher.update(instance.name.as_bytes());
+            hasher.update(&[0]);
+            hasher.update(&index.to_le_bytes());
+            let hash = hasher.finalize();
+
+            *index += 1;
+
+            referents.push(format!("RBX{}", &hash.to_hex()[..32]).to_uppercase());
+            visit(dom, instance.children(), Some(&hash), referents);
+        }
+    }
+
+    let mut referents = Vec::new();
+    visit(dom, top_level_ids, None, &mut referents);
+    referents
+}
+
+/// Replaces the referents in an XML file written by rbx_xml, both on items and
+/// in Ref properties. `referents` has the new referent of each item, in the
+/// order the items appear in the file.
+///
+/// Text inside properties always has its `<` escaped, so every `<Item` and
+/// `<Ref` found is a real tag.
+fn replace_referents(xml: &str, referents: &[String]) -> String {
+    let item_spans = value_spans(xml, "<Item ", "referent=\"", "\"");
+    let ref_spans = value_spans(xml, "<Ref ", ">", "<");
+
+    let new_referents: HashMap<&str, &str> = item_spans
+        .iter()
+        .map(|span| &xml[span.clone()])
+        .zip(referents.iter().map(String::as_str))
+        .collect();
+
+    let mut spans: Vec<Range<usize>> = item_spans.into_iter().chain(ref_spans).collect();
+    spans.sort_by_key(|span| span.start);
+
+    let mut output = String::with_capacity(xml.len());
+    let mut last_end = 0;
+
+    for span in spans {
+        output.push_str(&xml[last_end..span.start]);
+
+        let referent = &xml[span.clone()];
+        output.push_str(new_referents.get(referent).copied().unwrap_or(referent));
+
+        last_end = span.end;
+    }
+
+    output.push_str(&xml[last_end..]);
+    output
+}
+
+/// Finds the text between `start` and `end` that follows each `tag` in `xml`.
+fn value_spans(xml: &str, tag: &str, start: &str, end: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+
+    for (tag_index, _) in xml.match_indices(tag) {
+        let value_start = match xml[tag_index..].find(start) {
+            Some(offset) => tag_index + offset + start.len(),
+            None => continue,
+        };
+        let value_end = match xml[value_start..].find(end) {
+            Some(offset) => value_start + offset,
+            None => continue,
+        };
+
+        spans.push(value_start..value_end);
+    }
+
+    spans
+}
+
+/// Merges the tree into the place file at `base_path`, returning the merged
+/// tree.
+///
+/// Instances in the place that the tree doesn't describe are removed unless
+/// their parent ignores unknown instances, which is the default for project
+/// nodes without a `$path`. Those parents also keep any properties that the
+/// project doesn't set.
+fn merge_into_base(tree: &RojoTree, base_path: &Path) -> anyhow::Result<RojoTree> {
+    let root_id = tree.get_root_id();
+    let root_instance = tree.get_instance(root_id).unwrap();
+
+    if root_instance.class_name() != "DataModel" {
+        bail!("--base can only be used with projects that describe a place");
+    }
+
+    let base = read_model(base_path)?;
+    let base_root_id = base.root_ref();
+
+    // Place files don't store anything about the DataModel itself, so it
+    // takes the project's name to line up with the tree.
+    let base_snapshot = InstanceSnapshot::from_tree(base, base_root_id)
+        .name(root_instance.name())
+        .class_name("DataModel");
+
+    let mut merged = tree_from_snapshot(base_snapshot);
+    let merged_root_id = merged.get_root_id();
+
+    let snapshot = InstanceSnapshot::from_rojo_tree(tree, root_id);
+    let mut patch_set = compute_patch_set(Some(snapshot), &merged, merged_root_id);
+
+    let mut ignores_unknown = HashSet::new();
+
+    for update in &mut patch_set.updated_instances {
+        let ignore_unknown_instances = update
+            .changed_metadata
+            .as_ref()
+            .map_or(false, |metadata| metadata.ignore_unknown_instances);
+
+        if ignore_unknown_instances {
+            ignores_unknown.insert(update.id);
+            update.changed_properties.retain(|_, value| value.is_some());
+        }
+    }
+
+    patch_set.removed_instances.retain(|&id| {
+        let parent_id = merged.get_instance(id).unwrap().parent();
+        !ignores_unknown.contains(&parent_id)
+    });
+
+    apply_patch_set(&mut merged, patch_set);
+
+    Ok(merged)
+}
+
+/// Copies an instance and its descendants out of the tree into a new
+/// `WeakDom`, leaving out any descendant that `include` rejects along with
+/// everything under it.
+///
+/// Ref properties are pointed at the copied instances. Refs to instances that
+/// weren't copied are cleared.
+///
+/// If `sort_children` is set, siblings are copied in a stable order based on
+/// their name, class, and source file instead of the order the filesystem
+/// listed them in. Both rbx_binary and rbx_xml write properties sorted by name
+/// and number referents in the order instances are written, so a sorted tree
+/// always encodes to the same bytes. XML outputs then get referents that don't
+/// depend on that order from `write_xml_with_path_referents`.
+///
+/// Along with the copy, returns a map from the IDs of copied instances in the
+/// tree to their IDs in the copy.
+fn copy_subtree(
+    tree: &RojoTree,
+    root_id: Ref,
+    sort_children: bool,
+    include: impl Fn(Ref) -> bool,
+) -> (WeakDom, HashMap<Ref, Ref>) {
+    let source = tree.inner();
+    let root = source.get_by_ref(root_id).unwrap();
+
+    let children_of = |instance: &Instance| {
+        let mut children = instance.children().to_vec();
+
+        if sort_children {
+            children.sort_by_cached_key(|&child_id| {
+                let child = source.get_by_ref(child_id).unwrap();
+                let source_path = tree
+                    .get_metadata(child_id)
+                    .and_then(|metadata| metadata.instigating_source.as_ref())
+                    .map(|source| source.path().to_path_buf());
+
+                (child.name.clone(), child.class.clone(), source_path)
+            });
+        }
+
+        children
+    };
+
+    let mut dom = WeakDom::new(
+        InstanceBuilder::new(root.class.as_str())
+            .with_name(root.name.as_str())
+            .with_properties(root.properties.clone()),
+    );
+
+    let mut copied_ids = HashMap::new();
+    copied_ids.insert(root_id, dom.root_ref());
+
+    let mut to_copy: VecDeque<(Ref, Ref)> = children_of(root)
+        .into_iter()
+        .map(|child_id| (child_id, dom.root_ref()))
+        .collect();
+
+    while let Some((id, new_parent_id)) = to_copy.pop_front() {
+        if !include(id) {
+            continue;
+        }
+
+        let instance = source.get_by_ref(id).unwrap();
+        let builder = InstanceBuilder::new(instance.class.as_str())
+            .with_name(instance.name.as_str())
+            .with_properties(instance.properties.clone());
+
+        let new_id = dom.insert(new_parent_id, builder);
+        copied_ids.insert(id, new_id);
+
+        to_copy.extend(
+            children_of(instance)
+                .into_iter()
+                .map(|child_id| (child_id, new_id)),
+        );
+    }
+
+    for &new_id in copied_ids.values() {
+        let instance = dom.get_by_ref_mut(new_id).unwrap();
+
+        for value in instance.properties.values_mut() {
+            if let Variant::Ref(referent) = value {
+                *referent = copied_ids.get(referent).copied().unwrap_or_else(Ref::none);
+            }
+        }
+    }
+
+    (dom, copied_ids)
+}
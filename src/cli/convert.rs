@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+
+use super::{
+    build::{
+        detect_output_kind, read_json_model, read_model, write_dom, OutputKind,
+        UNKNOWN_OUTPUT_KIND_ERR,
+    },
+    resolve_path,
+};
+
+const UNKNOWN_CONVERT_KIND_ERR: &str = "Could not detect what kind of file to convert. \
+                                        Expected input file to end in .rbxl, .rbxlx, .rbxm, .rbxmx, or .model.json.";
+
+/// Converts a place or model file into another format.
+#[derive(Debug, Parser)]
+pub struct ConvertCommand {
+    /// The file to convert.
+    ///
+    /// Should end in .rbxm, .rbxmx, .model.json, .rbxl, or .rbxlx.
+    pub input: PathBuf,
+
+    /// Where to write the converted file. Models can only be converted into
+    /// other models, and places into other places.
+    ///
+    /// Should end in .rbxm, .rbxmx, .model.json, .rbxl, or .rbxlx.
+    #[clap(long, short)]
+    pub output: PathBuf,
+}
+
+impl ConvertCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let input_path = resolve_path(&self.input);
+
+        let input_kind = detect_output_kind(&input_path).context(UNKNOWN_CONVERT_KIND_ERR)?;
+        let output_kind = detect_output_kind(&self.output).context(UNKNOWN_OUTPUT_KIND_ERR)?;
+
+        if input_kind.is_place() != output_kind.is_place() {
+            bail!(
+                "Places can only be converted into places, and models into models. \
+                 Check the extension of the output file: {}",
+                self.output.display()
+            );
+        }
+
+        if input_kind == OutputKind::JsonModel {
+            let tree = read_json_model(&input_path)?;
+
+            return write_dom(tree.inner(), tree.get_root_id(), output_kind, &self.output);
+        }
+
+        // Binary and XML files are written straight from the DOM they were
+        // read into instead of going through a snapshot, which keeps Ref
+        // properties intact.
+        let dom = read_model(&input_path)?;
+
+        let root_id = if input_kind.is_place() {
+            dom.root_ref()
+        } else {
+            match dom.root().children() {
+                [root_id] => *root_id,
+                _ => bail!(
+                    "Rojo currently only supports model files with one top-level instance.\n\n \
+                     Check the model file at path {}",
+                    input_path.display()
+                ),
+            }
+        };
+
+        write_dom(&dom, root_id, output_kind, &self.output)
+    }
+}
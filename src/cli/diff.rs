@@ -0,0 +1,493 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    mem::forget,
+    path::PathBuf,
+};
+
+use anyhow::bail;
+use clap::Parser;
+use memofs::Vfs;
+use rbx_dom_weak::{types::Variant, WeakDom};
+use serde::Serialize;
+use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
+
+use crate::{
+    resolution::should_persist_property,
+    serve_session::ServeSession,
+    snapshot::{apply_patch_set, compute_patch_set, InstanceSnapshot, RojoTree},
+};
+
+use super::{build::read_model, resolve_path, GlobalOptions, ProjectOptions, ReportFormat};
+
+/// The longest property value that will be shown in text output before being
+/// cut off.
+const MAX_VALUE_LENGTH: usize = 60;
+
+/// Shows how building the project would differ from an existing place or model
+/// file.
+#[derive(Debug, Parser)]
+pub struct DiffCommand {
+    /// Path to the project to diff. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// The place or model file to compare the project against.
+    ///
+    /// Should end in .rbxl, .rbxlx, .rbxm, or .rbxmx.
+    #[clap(long, short)]
+    pub target: PathBuf,
+
+    /// The format to print the differences in, 'text' or 'json'. Defaults to
+    /// text.
+    #[clap(long, default_value = "text")]
+    pub format: ReportFormat,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
+}
+
+impl DiffCommand {
+    pub fn run(self, global: GlobalOptions) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+
+        log::trace!("Constructing in-memory filesystem");
+        let vfs = Vfs::new_default();
+
+        let session = ServeSession::new_with_context(
+            vfs,
+            &project_path,
+            self.project_options.instance_context(),
+        )?;
+        let target = read_model(&self.target)?;
+
+        let diff = diff_trees(&session.tree(), target)?;
+
+        match self.format {
+            ReportFormat::Text => print_diff(&diff, global.color.into())?,
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        }
+
+        // Avoid dropping ServeSession: it's potentially VERY expensive to drop
+        // and we're about to exit anyways.
+        forget(session);
+
+        Ok(())
+    }
+}
+
+/// The differences between a Rojo tree and an existing place or model,
+/// described from the perspective of the existing file.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct TreeDiff {
+    pub added: Vec<DiffInstance>,
+    pub removed: Vec<DiffInstance>,
+    pub changed: Vec<ChangedInstance>,
+}
+
+impl TreeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct DiffInstance {
+    pub path: String,
+    pub class_name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ChangedInstance {
+    pub path: String,
+    pub class_name: String,
+    pub properties: BTreeMap<String, PropertyChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct PropertyChange {
+    pub old: Option<Variant>,
+    pub new: Option<Variant>,
+}
+
+/// Computes what would change in `target` if it were replaced with the
+/// contents of `tree`.
+///
+/// `target` should be a DOM as returned by `read_model`. If `tree` describes a
+/// place, the target's root is compared with the DataModel; otherwise, the
+/// target must contain exactly one top-level instance.
+pub(super) fn diff_trees(tree: &RojoTree, target: WeakDom) -> anyhow::Result<TreeDiff> {
+    let root_id = tree.get_root_id();
+    let root_instance = tree.get_instance(root_id).unwrap();
+
+    let target_snapshot = if root_instance.class_name() == "DataModel" {
+        // Place files don't store anything about the DataModel itself, so it
+        // always matches.
+        let target_root = target.root_ref();
+
+        InstanceSnapshot::from_tree(target, target_root)
+            .name(root_instance.name())
+            .class_name("DataModel")
+    } else {
+        let top_level_ids = target.root().children().to_vec();
+
+        match top_level_ids.as_slice() {
+            [target_root] => InstanceSnapshot::from_tree(target, *target_root),
+            _ => bail!(
+                "The file to compare against must contain exactly one top-level \
+                 instance to be compared with a model project."
+            ),
+        }
+    };
+
+    Ok(diff_rojo_trees(tree, &tree_from_snapshot(target_snapshot)))
+}
+
+/// Builds a tree from a snapshot of instances read from a file.
+///
+/// Unlike `RojoTree::new`, this points Ref properties at the tree's own
+/// instances instead of the IDs they had in the file, so that they can be
+/// compared with the Ref properties of another tree.
+pub(super) fn tree_from_snapshot(snapshot: InstanceSnapshot) -> RojoTree {
+    let mut tree = RojoTree::new(InstanceSnapshot::new());
+    let root_id = tree.get_root_id();
+
+    let patch_set = compute_patch_set(Some(snapshot), &tree, root_id);
+    apply_patch_set(&mut tree, patch_set);
+
+    tree
+}
+
+/// Computes what would change in `target_tree` if it were replaced with the
+/// contents of `tree`. The roots of both trees are compared with each other.
+pub(super) fn diff_rojo_trees(tree: &RojoTree, target_tree: &RojoTree) -> TreeDiff {
+    let root_id = tree.get_root_id();
+    let is_place = tree.get_instance(root_id).unwrap().class_name() == "DataModel";
+    let target_root_id = target_tree.get_root_id();
+
+    let snapshot = InstanceSnapshot::from_rojo_tree(tree, root_id);
+    let patch_set = compute_patch_set(Some(snapshot), target_tree, target_root_id);
+
+    let mut diff = TreeDiff::default();
+
+    for patch in patch_set.added_instances {
+        let path = if is_place && patch.parent_id == target_root_id {
+            patch.instance.name.into_owned()
+        } else {
+            let parent_path = target_tree.get_full_name(patch.parent_id);
+            format!("{}.{}", parent_path, patch.instance.name)
+        };
+
+        diff.added.push(DiffInstance {
+            path,
+            class_name: patch.instance.class_name.into_owned(),
+        });
+    }
+
+    for id in patch_set.removed_instances {
+        let instance = target_tree.get_instance(id).unwrap();
+
+        diff.removed.push(DiffInstance {
+            path: target_tree.get_full_name(id),
+            class_name: instance.class_name().to_owned(),
+        });
+    }
+
+    for update in patch_set.updated_instances {
+        let instance = target_tree.get_instance(update.id).unwrap();
+        let class_name = instance.class_name();
+        let mut properties = BTreeMap::new();
+
+        if let Some(new_class_name) = update.changed_class_name {
+            properties.insert(
+                "ClassName".to_owned(),
+                PropertyChange {
+                    old: Some(Variant::String(class_name.to_owned())),
+                    new: Some(Variant::String(new_class_name)),
+                },
+            );
+        }
+
+        for (key, new_value) in update.changed_properties {
+            let old_value = instance.properties().get(&key);
+
+            if old_value == new_value.as_ref() {
+                continue;
+            }
+
+            // A property missing from one side is only a real change if the
+            // other side holds something besides the default value.
+            let present_value = match (old_value, &new_value) {
+                (Some(value), None) | (None, Some(value)) => Some(value),
+                _ => None,
+            };
+
+            if let Some(value) = present_value {
+                if !is_significant_property(class_name, &key, value) {
+                    continue;
+                }
+            }
+
+            properties.insert(
+                key,
+                PropertyChange {
+                    old: old_value.cloned(),
+                    new: new_value,
+                },
+            );
+        }
+
+        if properties.is_empty() {
+            continue;
+        }
+
+        diff.changed.push(ChangedInstance {
+            path: target_tree.get_full_name(update.id),
+            class_name: class_name.to_owned(),
+            properties,
+        });
+    }
+
+    diff.added.sort_by(|a, b| a.path.cmp(&b.path));
+    diff.removed.sort_by(|a, b| a.path.cmp(&b.path));
+    diff.changed.sort_by(|a, b| a.path.cmp(&b.path));
+
+    diff
+}
+
+/// Whether a property that only one side has is worth reporting.
+///
+/// Ref properties that point at an instance always are, since the patch
+/// already points them at the matching instance in the target when there is
+/// one.
+fn is_significant_property(class_name: &str, key: &str, value: &Variant) -> bool {
+    match value {
+        Variant::Ref(referent) => referent.is_some(),
+        _ => should_persist_property(class_name, key, value),
+    }
+}
+
+/// Prints a human-readable version of the given diff to stdout.
+pub(super) fn print_diff(diff: &TreeDiff, color: ColorChoice) -> io::Result<()> {
+    let writer = BufferWriter::stdout(color);
+    let mut buffer = writer.buffer();
+
+    if diff.is_empty() {
+        writeln!(&mut buffer, "No differences found.")?;
+        return writer.print(&buffer);
+    }
+
+    let mut green = ColorSpec::new();
+    green.set_fg(Some(Color::Green));
+
+    let mut red = ColorSpec::new();
+    red.set_fg(Some(Color::Red));
+
+    let mut yellow = ColorSpec::new();
+    yellow.set_fg(Some(Color::Yellow));
+
+    for instance in &diff.added {
+        buffer.set_color(&green)?;
+        writeln!(&mut buffer, "+ {} ({})", instance.path, instance.class_name)?;
+    }
+
+    for instance in &diff.removed {
+        buffer.set_color(&red)?;
+        writeln!(&mut buffer, "- {} ({})", instance.path, instance.class_name)?;
+    }
+
+    for instance in &diff.changed {
+        buffer.set_color(&yellow)?;
+        writeln!(&mut buffer, "~ {} ({})", instance.path, instance.class_name)?;
+
+        buffer.set_color(&ColorSpec::new())?;
+        for (key, change) in &instance.properties {
+            writeln!(
+                &mut buffer,
+                "    {}: {} -> {}",
+                key,
+                describe_value(change.old.as_ref()),
+                describe_value(change.new.as_ref())
+            )?;
+        }
+    }
+
+    buffer.set_color(&ColorSpec::new())?;
+    writeln!(
+        &mut buffer,
+        "\n{} added, {} removed, {} changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    )?;
+
+    writer.print(&buffer)
+}
+
+fn describe_value(value: Option<&Variant>) -> String {
+    let value = match value {
+        Some(value) => value,
+        None => return "(none)".to_owned(),
+    };
+
+    let description = format!("{:?}", value);
+
+    if description.chars().count() > MAX_VALUE_LENGTH {
+        let truncated: String = description.chars().take(MAX_VALUE_LENGTH).collect();
+        format!("{}...", truncated)
+    } else {
+        description
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    /// Creates a DOM like `read_model` would return for a model containing a
+    /// car, whose PrimaryPart is the part with the given name.
+    fn car_dom(primary_part: &str) -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let car = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Model").with_name("Car"),
+        );
+
+        let body = dom.insert(car, InstanceBuilder::new("Part").with_name("Body"));
+        let wheel = dom.insert(car, InstanceBuilder::new("Part").with_name("Wheel"));
+        let primary_part = if primary_part == "Body" { body } else { wheel };
+
+        dom.get_by_ref_mut(car)
+            .unwrap()
+            .properties
+            .insert("PrimaryPart".to_owned(), Variant::Ref(primary_part));
+
+        dom
+    }
+
+    fn car_tree(primary_part: &str) -> RojoTree {
+        let dom = car_dom(primary_part);
+        let car = dom.root().children()[0];
+
+        tree_from_snapshot(InstanceSnapshot::from_tree(dom, car))
+    }
+
+    /// Creates a DOM like `read_model` would return for a model containing a
+    /// folder with a StringValue for each of the given names and values.
+    fn folder_dom(children: &[(&str, &str)]) -> WeakDom {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let folder = dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Folder").with_name("Root"),
+        );
+
+        for (name, value) in children {
+            dom.insert(
+                folder,
+                InstanceBuilder::new("StringValue")
+                    .with_name(*name)
+                    .with_property("Value", Variant::String((*value).to_owned())),
+            );
+        }
+
+        dom
+    }
+
+    fn folder_tree(children: &[(&str, &str)]) -> RojoTree {
+        let dom = folder_dom(children);
+        let folder = dom.root().children()[0];
+
+        tree_from_snapshot(InstanceSnapshot::from_tree(dom, folder))
+    }
+
+    #[test]
+    fn identical_trees_have_no_differences() {
+        let children = [("Greeting", "Hello"), ("Farewell", "Goodbye")];
+        let diff = diff_trees(&folder_tree(&children), folder_dom(&children)).unwrap();
+
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn added_removed_and_changed_instances() {
+        let tree = folder_tree(&[("Greeting", "Hello"), ("Added", "New")]);
+        let target = folder_dom(&[("Greeting", "Hi"), ("Removed", "Old")]);
+
+        let diff = diff_trees(&tree, target).unwrap();
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].path, "Root.Added");
+        assert_eq!(diff.added[0].class_name, "StringValue");
+
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].path, "Root.Removed");
+        assert_eq!(diff.removed[0].class_name, "StringValue");
+
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].path, "Root.Greeting");
+
+        let change = &diff.changed[0].properties["Value"];
+        assert_eq!(change.old, Some(Variant::String("Hi".to_owned())));
+        assert_eq!(change.new, Some(Variant::String("Hello".to_owned())));
+    }
+
+    #[test]
+    fn missing_default_values_are_not_changes() {
+        let mut target = folder_dom(&[("Empty", "")]);
+        let tree = folder_tree(&[("Empty", "")]);
+
+        let folder = target.root().children()[0];
+        let empty = target.get_by_ref(folder).unwrap().children()[0];
+        target
+            .get_by_ref_mut(empty)
+            .unwrap()
+            .properties
+            .remove("Value");
+
+        let diff = diff_trees(&tree, target).unwrap();
+
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn place_children_are_named_without_the_data_model() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_name("Place"));
+        dom.insert(
+            dom.root_ref(),
+            InstanceBuilder::new("Workspace").with_name("Workspace"),
+        );
+        let root_id = dom.root_ref();
+        let tree = tree_from_snapshot(InstanceSnapshot::from_tree(dom, root_id));
+
+        let target = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let diff = diff_trees(&tree, target).unwrap();
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].path, "Workspace");
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn refs_to_the_same_instance_match() {
+        let diff = diff_trees(&car_tree("Body"), car_dom("Body")).unwrap();
+
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn refs_to_different_instances_differ() {
+        let diff = diff_trees(&car_tree("Body"), car_dom("Wheel")).unwrap();
+
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].path, "Car");
+        assert_eq!(
+            diff.changed[0].properties.keys().collect::<Vec<_>>(),
+            ["PrimaryPart"]
+        );
+    }
+}
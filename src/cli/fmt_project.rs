@@ -1,9 +1,9 @@
-use std::path::PathBuf;
+use std::{borrow::Cow, path::PathBuf};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use clap::Parser;
 
-use crate::project::Project;
+use crate::{jsonc, project::Project};
 
 use super::resolve_path;
 
@@ -21,6 +21,45 @@ impl FmtProjectCommand {
         let project = Project::load_fuzzy(&base_path)?
             .context("A project file is required to run 'rojo fmt-project'")?;
 
+        // Writing the project back would replace it with the project merged
+        // with everything it extends.
+        if project.extends.is_some() {
+            bail!(
+                "{} extends another project, which 'rojo fmt-project' can't format yet",
+                project.file_location.display()
+            );
+        }
+
+        // The same goes for variables, which would be replaced with their
+        // values.
+        let contents = fs_err::read_to_string(&project.file_location)?;
+        if contents.contains("${") {
+            bail!(
+                "{} uses variables, which 'rojo fmt-project' can't format yet",
+                project.file_location.display()
+            );
+        }
+
+        let is_json = project
+            .file_location
+            .extension()
+            .map_or(false, |extension| extension == "json");
+
+        if !is_json {
+            bail!(
+                "{} is not a JSON project file, which is all 'rojo fmt-project' can format",
+                project.file_location.display()
+            );
+        }
+
+        // Comments would be lost when the project is written back.
+        if let Cow::Owned(_) = jsonc::strip(contents.as_bytes()) {
+            bail!(
+                "{} contains comments or trailing commas, which 'rojo fmt-project' can't keep yet",
+                project.file_location.display()
+            );
+        }
+
         let serialized = serde_json::to_string_pretty(&project)
             .context("could not re-encode project file as JSON")?;
 
@@ -1,27 +1,49 @@
 //! Defines Rojo's CLI through clap types.
 
 mod build;
+mod convert;
+mod diff;
 mod doc;
 mod fmt_project;
 mod init;
 mod plugin;
+mod remap;
+mod schema;
 mod serve;
 mod sourcemap;
+mod syncback;
+mod types;
 mod upload;
+mod validate;
 
-use std::{borrow::Cow, env, path::Path, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use clap::Parser;
 use thiserror::Error;
 
+use crate::snapshot::InstanceContext;
+
 pub use self::build::BuildCommand;
+pub use self::convert::ConvertCommand;
+pub use self::diff::DiffCommand;
 pub use self::doc::DocCommand;
 pub use self::fmt_project::FmtProjectCommand;
 pub use self::init::{InitCommand, InitKind};
 pub use self::plugin::{PluginCommand, PluginSubcommand};
+pub use self::remap::RemapCommand;
+pub use self::schema::SchemaCommand;
 pub use self::serve::ServeCommand;
 pub use self::sourcemap::SourcemapCommand;
+pub use self::syncback::SyncbackCommand;
+pub use self::types::TypesCommand;
 pub use self::upload::UploadCommand;
+pub use self::validate::ValidateCommand;
 
 /// Command line options that Rojo accepts, defined using the clap crate.
 #[derive(Debug, Parser)]
@@ -40,12 +62,19 @@ impl Options {
         match self.subcommand {
             Subcommand::Init(subcommand) => subcommand.run(),
             Subcommand::Serve(subcommand) => subcommand.run(self.global),
-            Subcommand::Build(subcommand) => subcommand.run(),
+            Subcommand::Build(subcommand) => subcommand.run(self.global),
+            Subcommand::Convert(subcommand) => subcommand.run(),
+            Subcommand::Diff(subcommand) => subcommand.run(self.global),
             Subcommand::Upload(subcommand) => subcommand.run(),
             Subcommand::Sourcemap(subcommand) => subcommand.run(),
+            Subcommand::Types(subcommand) => subcommand.run(),
+            Subcommand::Remap(subcommand) => subcommand.run(),
+            Subcommand::Syncback(subcommand) => subcommand.run(),
+            Subcommand::Validate(subcommand) => subcommand.run(self.global),
             Subcommand::FmtProject(subcommand) => subcommand.run(),
             Subcommand::Doc(subcommand) => subcommand.run(),
             Subcommand::Plugin(subcommand) => subcommand.run(),
+            Subcommand::Schema(subcommand) => subcommand.run(),
         }
     }
 }
@@ -109,16 +138,116 @@ pub struct ColorChoiceParseError {
     attempted: String,
 }
 
+/// The formats that commands which report on a project, like `rojo diff`, can
+/// print in.
+#[derive(Debug, Clone, Copy)]
+pub enum ReportFormat {
+    /// Human-readable text.
+    Text,
+
+    /// A JSON document, suitable for editors and other tools.
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = ReportFormatParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(ReportFormatParseError {
+                attempted: source.to_owned(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid format '{attempted}'. Valid values are: text, json")]
+pub struct ReportFormatParseError {
+    attempted: String,
+}
+
+/// Options that change how a project is loaded, shared by the commands that
+/// load projects.
+#[derive(Debug, Default, Parser)]
+pub struct ProjectOptions {
+    /// Sets a variable used in project files, like --var PLACE_ID=1234. Can be
+    /// given more than once. Overrides the project's variables and environment
+    /// variables of the same name. Only projects that declare `variables` use
+    /// it.
+    #[clap(long = "var", value_name = "KEY=VALUE")]
+    pub variables: Vec<Variable>,
+
+    /// The name of a profile defined in the project file to apply, like
+    /// production.
+    #[clap(long)]
+    pub profile: Option<String>,
+}
+
+impl ProjectOptions {
+    /// Creates the context that projects are snapshotted with.
+    pub(super) fn instance_context(&self) -> InstanceContext {
+        let variables: BTreeMap<String, String> = self
+            .variables
+            .iter()
+            .map(|variable| (variable.name.clone(), variable.value.clone()))
+            .collect();
+
+        let mut context = InstanceContext::default();
+        context.set_variables(variables);
+        context.set_profile(self.profile.as_deref());
+        context
+    }
+}
+
+/// A variable given on the command line as `KEY=VALUE`.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for Variable {
+    type Err = VariableParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok(Variable {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            }),
+            _ => Err(VariableParseError {
+                attempted: source.to_owned(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid variable '{attempted}'. Variables are given as KEY=VALUE")]
+pub struct VariableParseError {
+    attempted: String,
+}
+
 #[derive(Debug, Parser)]
 pub enum Subcommand {
     Init(InitCommand),
     Serve(ServeCommand),
     Build(BuildCommand),
+    Convert(ConvertCommand),
+    Diff(DiffCommand),
     Upload(UploadCommand),
     Sourcemap(SourcemapCommand),
+    Types(TypesCommand),
+    Remap(RemapCommand),
+    Syncback(SyncbackCommand),
+    Validate(ValidateCommand),
     FmtProject(FmtProjectCommand),
     Doc(DocCommand),
     Plugin(PluginCommand),
+    Schema(SchemaCommand),
 }
 
 pub(super) fn resolve_path(path: &Path) -> Cow<'_, Path> {
@@ -128,3 +257,21 @@ pub(super) fn resolve_path(path: &Path) -> Cow<'_, Path> {
         Cow::Owned(env::current_dir().unwrap().join(path))
     }
 }
+
+/// Makes paths relative to the working directory when possible, which is what
+/// editors and CI annotations expect.
+pub(super) fn display_path(path: &Path) -> PathBuf {
+    match env::current_dir() {
+        Ok(current_dir) => path
+            .strip_prefix(&current_dir)
+            .unwrap_or(path)
+            .to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Escapes a key so that it can be used as part of a JSON pointer, as
+/// described in RFC 6901.
+pub(super) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,301 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    iter,
+    mem::forget,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use memofs::Vfs;
+
+use crate::{serve_session::ServeSession, snapshot::RojoTree};
+
+use super::{display_path, resolve_path, ProjectOptions};
+
+/// Rewrites instance paths in logs and stack traces, like
+/// ReplicatedStorage.Shared.Net:42, into the files they came from, like
+/// src/shared/Net.luau:42.
+///
+/// Reads from stdin and writes to stdout, one line at a time.
+#[derive(Debug, Parser)]
+pub struct RemapCommand {
+    /// Path to the project that the logs came from. Defaults to the current
+    /// directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// Whether to write absolute file paths instead of paths relative to the
+    /// current directory.
+    #[clap(long)]
+    pub absolute: bool,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
+}
+
+impl RemapCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+
+        log::trace!("Constructing in-memory filesystem");
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let session = ServeSession::new_with_context(
+            vfs,
+            &project_path,
+            self.project_options.instance_context(),
+        )?;
+
+        let script_paths = script_paths(&session.tree())
+            .into_iter()
+            .map(|(full_name, path)| {
+                let path = if self.absolute {
+                    path
+                } else {
+                    display_path(&path)
+                };
+
+                (full_name, path)
+            })
+            .collect();
+        let remapper = Remapper::new(script_paths);
+
+        // Avoid dropping ServeSession: it's potentially VERY expensive to drop
+        // and we're about to exit anyways.
+        forget(session);
+
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+
+        for line in stdin.lock().lines() {
+            let line = line?;
+            let remapped = remapper.remap_line(&line);
+
+            writeln!(stdout, "{}", remapped)?;
+
+            // Logs are often piped in as they're written, so each line is
+            // passed along right away.
+            stdout.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the source file of every script in the tree, keyed by the script's
+/// dotted path.
+///
+/// Scripts made from a folder with an init script map to that init script.
+/// Scripts without a Luau file, like ones from model files, are left out since
+/// their line numbers don't correspond to any file.
+fn script_paths(tree: &RojoTree) -> HashMap<String, PathBuf> {
+    let mut paths = HashMap::new();
+
+    for instance in tree.descendants(tree.get_root_id()) {
+        if !matches!(
+            instance.class_name(),
+            "Script" | "LocalScript" | "ModuleScript"
+        ) {
+            continue;
+        }
+
+        let source_file = instance
+            .metadata()
+            .relevant_paths
+            .iter()
+            .find(|path| is_luau_file(path) && path.is_file());
+
+        if let Some(source_file) = source_file {
+            paths.insert(tree.get_full_name(instance.id()), source_file.clone());
+        }
+    }
+
+    paths
+}
+
+fn is_luau_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("lua" | "luau")
+    )
+}
+
+/// Rewrites the dotted paths of scripts in text into their source files.
+struct Remapper {
+    script_paths: HashMap<String, PathBuf>,
+
+    /// The length of the longest script path, which bounds how far ahead a
+    /// match can end.
+    longest_name: usize,
+}
+
+impl Remapper {
+    fn new(script_paths: HashMap<String, PathBuf>) -> Self {
+        let longest_name = script_paths.keys().map(String::len).max().unwrap_or(0);
+
+        Self {
+            script_paths,
+            longest_name,
+        }
+    }
+
+    /// Replaces every script path in the line with the path of its source
+    /// file.
+    ///
+    /// A script path can only start and end where a dotted path could, so
+    /// Shared.Net inside Shared.Network isn't a reference to Shared.Net. At
+    /// each place a path could start, the longest script path found there
+    /// wins.
+    fn remap_line(&self, line: &str) -> String {
+        let mut remapped = String::with_capacity(line.len());
+        let mut last_end = 0;
+        let mut previous = None;
+
+        for (start, c) in line.char_indices() {
+            let can_start =
+                start >= last_end && !previous.map_or(false, |p| is_name_char(p) || p == '.');
+            previous = Some(c);
+
+            if !can_start {
+                continue;
+            }
+
+            if let Some((end, source_path)) = self.longest_match(line, start) {
+                remapped.push_str(&line[last_end..start]);
+                remapped.push_str(&source_path.to_string_lossy());
+                last_end = end;
+            }
+        }
+
+        remapped.push_str(&line[last_end..]);
+        remapped
+    }
+
+    /// Finds the longest script path starting at `start` that isn't followed
+    /// by more of a dotted path, returning where it ends and its source file.
+    fn longest_match(&self, line: &str, start: usize) -> Option<(usize, &PathBuf)> {
+        let rest = &line[start..];
+        let limit = rest.len().min(self.longest_name);
+
+        rest.char_indices()
+            .map(|(offset, _)| offset)
+            .chain(iter::once(rest.len()))
+            .take_while(|&offset| offset <= limit)
+            .filter(|&offset| offset > 0 && !continues_path(&rest[offset..]))
+            .filter_map(|offset| {
+                self.script_paths
+                    .get(&rest[..offset])
+                    .map(|source_path| (start + offset, source_path))
+            })
+            .last()
+    }
+}
+
+/// Whether text directly after a match continues the dotted path, like
+/// `work` after Shared.Net in Shared.Network.
+fn continues_path(after: &str) -> bool {
+    let mut chars = after.chars();
+
+    match chars.next() {
+        Some('.') => chars.next().map_or(false, is_name_char),
+        Some(c) => is_name_char(c),
+        None => false,
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn remapper() -> Remapper {
+        let script_paths = [
+            ("ReplicatedStorage.Shared", "src/shared/init.luau"),
+            ("ReplicatedStorage.Shared.Net", "src/shared/Net.luau"),
+            ("ServerScriptService.Main", "src/server/Main.server.luau"),
+            ("StarterGui.Health Bar", "src/gui/Health Bar.client.luau"),
+        ];
+
+        Remapper::new(
+            script_paths
+                .iter()
+                .map(|(name, path)| (name.to_string(), PathBuf::from(path)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn remaps_stack_trace() {
+        let remapper = remapper();
+
+        assert_eq!(
+            remapper.remap_line("ReplicatedStorage.Shared.Net:42: attempt to index nil"),
+            "src/shared/Net.luau:42: attempt to index nil"
+        );
+        assert_eq!(
+            remapper.remap_line("Script 'ServerScriptService.Main', Line 7"),
+            "Script 'src/server/Main.server.luau', Line 7"
+        );
+    }
+
+    #[test]
+    fn prefers_longest_path() {
+        let remapper = remapper();
+
+        assert_eq!(
+            remapper
+                .remap_line("ReplicatedStorage.Shared:3 required ReplicatedStorage.Shared.Net:9"),
+            "src/shared/init.luau:3 required src/shared/Net.luau:9"
+        );
+    }
+
+    #[test]
+    fn remaps_names_with_spaces() {
+        assert_eq!(
+            remapper().remap_line("StarterGui.Health Bar:12: oops"),
+            "src/gui/Health Bar.client.luau:12: oops"
+        );
+    }
+
+    #[test]
+    fn ignores_partial_paths() {
+        let remapper = remapper();
+
+        // Part of a longer name.
+        assert_eq!(
+            remapper.remap_line("ReplicatedStorage.Shared.Network:1"),
+            "ReplicatedStorage.Shared.Network:1"
+        );
+
+        // A descendant of a script that isn't a script itself.
+        assert_eq!(
+            remapper.remap_line("ReplicatedStorage.Shared.Net.Config"),
+            "ReplicatedStorage.Shared.Net.Config"
+        );
+
+        // The end of a longer path.
+        assert_eq!(
+            remapper.remap_line("Workspace.ServerScriptService.Main:1"),
+            "Workspace.ServerScriptService.Main:1"
+        );
+    }
+
+    #[test]
+    fn keeps_lines_without_scripts() {
+        assert_eq!(remapper().remap_line(""), "");
+        assert_eq!(
+            remapper().remap_line("Hello, world! 🎉"),
+            "Hello, world! 🎉"
+        );
+        assert_eq!(
+            Remapper::new(HashMap::new()).remap_line("ReplicatedStorage.Shared:1"),
+            "ReplicatedStorage.Shared:1"
+        );
+    }
+}
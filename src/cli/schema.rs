@@ -0,0 +1,309 @@
+use std::{collections::BTreeSet, path::PathBuf, str::FromStr};
+
+use clap::Parser;
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::{
+    project::Project,
+    snapshot_middleware::{AdjacentMetadata, DirectoryMetadata, JsonModel},
+};
+
+/// Generates a JSON Schema for one of Rojo's file formats, which editors can
+/// use to validate and autocomplete those files.
+#[derive(Debug, Parser)]
+pub struct SchemaCommand {
+    /// The file format to describe. Valid values are project, meta, init-meta,
+    /// and model.
+    pub format: SchemaFormat,
+
+    /// Where to output the schema. Omit this to use stdout instead of writing
+    /// to a file.
+    #[clap(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+impl SchemaCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let schema = generate_schema(self.format);
+        let contents = serde_json::to_string_pretty(&schema)?;
+
+        match &self.output {
+            Some(output_path) => {
+                fs_err::write(output_path, contents + "\n")?;
+                println!("Created schema at {}", output_path.display());
+            }
+            None => println!("{}", contents),
+        }
+
+        Ok(())
+    }
+}
+
+/// The file formats that `rojo schema` can describe.
+#[derive(Debug, Clone, Copy)]
+pub enum SchemaFormat {
+    /// `.project.json` files.
+    Project,
+
+    /// `.meta.json` files next to the file they describe.
+    Meta,
+
+    /// `init.meta.json` files, which describe the folder they're in.
+    InitMeta,
+
+    /// `.model.json` files.
+    Model,
+}
+
+impl FromStr for SchemaFormat {
+    type Err = SchemaFormatParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "project" => Ok(SchemaFormat::Project),
+            "meta" => Ok(SchemaFormat::Meta),
+            "init-meta" => Ok(SchemaFormat::InitMeta),
+            "model" => Ok(SchemaFormat::Model),
+            _ => Err(SchemaFormatParseError {
+                attempted: source.to_owned(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid schema format '{attempted}'. Valid values are: project, meta, init-meta, model")]
+pub struct SchemaFormatParseError {
+    attempted: String,
+}
+
+/// Generates the schema for a format from the types Rojo reads it into, then
+/// fills in the class and property names from the reflection database.
+fn generate_schema(format: SchemaFormat) -> Value {
+    let (mut schema, class_name_pointers, properties_pointers) = match format {
+        SchemaFormat::Project => (
+            schema_for::<Project>(),
+            vec!["/definitions/ProjectNode/properties/$className"],
+            vec!["/definitions/ProjectNode/properties/$properties"],
+        ),
+        SchemaFormat::Meta => (
+            schema_for::<AdjacentMetadata>(),
+            vec![],
+            vec!["/properties/properties"],
+        ),
+        SchemaFormat::InitMeta => (
+            schema_for::<DirectoryMetadata>(),
+            vec!["/properties/className"],
+            vec!["/properties/properties"],
+        ),
+        SchemaFormat::Model => (
+            schema_for::<JsonModel>(),
+            vec![
+                "/properties/className",
+                "/definitions/JsonModel/properties/className",
+            ],
+            vec![
+                "/properties/properties",
+                "/definitions/JsonModel/properties/properties",
+            ],
+        ),
+    };
+
+    let database = rbx_reflection_database::get();
+
+    let class_names: BTreeSet<&str> = database.classes.keys().map(|name| name.as_ref()).collect();
+
+    // Name and Parent are set by where an instance is, not by its properties.
+    let property_names: BTreeSet<&str> = database
+        .classes
+        .values()
+        .flat_map(|class| class.properties.keys())
+        .map(|name| name.as_ref())
+        .filter(|name| !matches!(*name, "Name" | "Parent"))
+        .collect();
+
+    for pointer in class_name_pointers {
+        if let Some(class_name) = schema.pointer_mut(pointer) {
+            add_known_values(class_name, &class_names);
+        }
+    }
+
+    for pointer in properties_pointers {
+        if let Some(Value::Object(properties)) = schema.pointer_mut(pointer) {
+            let mut property_name = json!({ "type": "string" });
+            add_known_values(&mut property_name, &property_names);
+
+            properties.insert("propertyNames".to_owned(), property_name);
+        }
+    }
+
+    schema
+}
+
+fn schema_for<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| settings.option_add_null_type = false)
+        .into_generator();
+
+    serde_json::to_value(generator.into_root_schema_for::<T>())
+        .expect("schemas can always be serialized")
+}
+
+/// Suggests the given values for a string in the schema, while still allowing
+/// any other string so that files aren't rejected when Roblox adds classes or
+/// properties that Rojo doesn't know about yet.
+fn add_known_values(schema: &mut Value, known_values: &BTreeSet<&str>) {
+    if let Value::Object(schema) = schema {
+        schema.insert(
+            "anyOf".to_owned(),
+            json!([{ "enum": known_values }, { "type": "string" }]),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns the known values that `add_known_values` suggested for the
+    /// string at `pointer`.
+    fn known_values<'a>(schema: &'a Value, pointer: &str) -> Vec<&'a str> {
+        let any_of = schema
+            .pointer(&format!("{}/anyOf", pointer))
+            .unwrap_or_else(|| panic!("{} had no known values", pointer));
+
+        assert_eq!(any_of[1], json!({ "type": "string" }));
+
+        any_of[0]["enum"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|value| value.as_str().unwrap())
+            .collect()
+    }
+
+    /// Checks a value against the parts of a schema that describe its type.
+    /// This is only as much of JSON Schema as the tests below need.
+    fn accepts(root: &Value, schema: &Value, value: &Value) -> bool {
+        if let Some(Value::String(pointer)) = schema.get("$ref") {
+            let definition = root.pointer(pointer.trim_start_matches('#')).unwrap();
+            return accepts(root, definition, value);
+        }
+
+        if let Some(Value::Array(any_of)) = schema.get("anyOf") {
+            if !any_of.iter().any(|schema| accepts(root, schema, value)) {
+                return false;
+            }
+        }
+
+        let type_matches = match schema.get("type").and_then(Value::as_str) {
+            Some("integer") => value.is_u64() || value.is_i64(),
+            Some("string") => value.is_string(),
+            Some("array") => match value {
+                Value::Array(items) => items
+                    .iter()
+                    .all(|item| accepts(root, &schema["items"], item)),
+                _ => false,
+            },
+            _ => true,
+        };
+
+        let in_range = match value.as_f64() {
+            Some(number) => {
+                schema["minimum"].as_f64().map_or(true, |min| number >= min)
+                    && schema["maximum"].as_f64().map_or(true, |max| number <= max)
+            }
+            None => true,
+        };
+
+        type_matches && in_range
+    }
+
+    #[test]
+    fn project_numbers_accept_variables() {
+        let schema = generate_schema(SchemaFormat::Project);
+        let project = json!({
+            "placeId": "${PLACE_ID}",
+            "gameId": 1818,
+            "servePort": "${PORT:-34872}",
+            "servePlaceIds": ["${PLACE_ID}", 1818],
+        });
+
+        for (key, value) in project.as_object().unwrap() {
+            let property = &schema["properties"][key];
+            assert!(property.is_object(), "{} isn't in the schema", key);
+
+            assert!(
+                accepts(&schema, property, value),
+                "{} didn't accept {}",
+                key,
+                value
+            );
+        }
+
+        assert!(!accepts(
+            &schema,
+            &schema["properties"]["placeId"],
+            &json!(true)
+        ));
+        assert!(!accepts(
+            &schema,
+            &schema["properties"]["servePort"],
+            &json!(70000)
+        ));
+    }
+
+    #[test]
+    fn project_class_names() {
+        let schema = generate_schema(SchemaFormat::Project);
+        let class_names = known_values(&schema, "/definitions/ProjectNode/properties/$className");
+
+        assert!(class_names.contains(&"Part"));
+        assert!(class_names.contains(&"DataModel"));
+    }
+
+    #[test]
+    fn project_property_names() {
+        let schema = generate_schema(SchemaFormat::Project);
+        let property_names = known_values(
+            &schema,
+            "/definitions/ProjectNode/properties/$properties/propertyNames",
+        );
+
+        assert!(property_names.contains(&"Anchored"));
+        assert!(!property_names.contains(&"Name"));
+        assert!(!property_names.contains(&"Parent"));
+    }
+
+    #[test]
+    fn globs_are_strings() {
+        let schema = generate_schema(SchemaFormat::Project);
+
+        assert_eq!(schema["definitions"]["Glob"], json!({ "type": "string" }));
+    }
+
+    #[test]
+    fn meta_formats() {
+        let meta = generate_schema(SchemaFormat::Meta);
+        assert!(meta.pointer("/properties/className").is_none());
+        known_values(&meta, "/properties/properties/propertyNames");
+
+        let init_meta = generate_schema(SchemaFormat::InitMeta);
+        assert!(known_values(&init_meta, "/properties/className").contains(&"Configuration"));
+        known_values(&init_meta, "/properties/properties/propertyNames");
+    }
+
+    #[test]
+    fn model_class_names() {
+        let schema = generate_schema(SchemaFormat::Model);
+
+        // The root model and its children are described separately.
+        assert!(known_values(&schema, "/properties/className").contains(&"Folder"));
+        assert!(
+            known_values(&schema, "/definitions/JsonModel/properties/className")
+                .contains(&"Folder")
+        );
+    }
+}
@@ -11,7 +11,7 @@ use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 
 use crate::{serve_session::ServeSession, web::LiveServer};
 
-use super::{resolve_path, GlobalOptions};
+use super::{resolve_path, GlobalOptions, ProjectOptions};
 
 const DEFAULT_BIND_ADDRESS: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
 const DEFAULT_PORT: u16 = 34872;
@@ -31,6 +31,9 @@ pub struct ServeCommand {
     /// it has none.
     #[clap(long)]
     pub port: Option<u16>,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
 }
 
 impl ServeCommand {
@@ -39,7 +42,11 @@ impl ServeCommand {
 
         let vfs = Vfs::new_default();
 
-        let session = Arc::new(ServeSession::new(vfs, &project_path)?);
+        let session = Arc::new(ServeSession::new_with_hooks(
+            vfs,
+            &project_path,
+            self.project_options.instance_context(),
+        )?);
 
         let ip = self
             .address
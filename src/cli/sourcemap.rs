@@ -1,35 +1,59 @@
 use std::{
-    io::{BufWriter, Write},
+    collections::HashSet,
+    io::{self, BufRead, BufWriter, Write},
     mem::forget,
     path::{Path, PathBuf},
+    str::FromStr,
+    thread,
 };
 
 use clap::Parser;
 use fs_err::File;
+use futures::{
+    channel::mpsc,
+    future::{self, Either},
+    StreamExt,
+};
 use memofs::Vfs;
 use rbx_dom_weak::types::Ref;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tokio::runtime::Runtime;
 
 use crate::{
+    glob::Glob,
+    project::Project,
     serve_session::ServeSession,
-    snapshot::{InstanceWithMeta, RojoTree},
+    snapshot::{AppliedPatchSet, InstanceWithMeta, InstigatingSource, RojoTree},
 };
 
-use super::resolve_path;
-
-const PATH_STRIP_FAILED_ERR: &str = "Failed to create relative paths for project file!";
+use super::{escape_pointer, resolve_path, ProjectOptions};
 
 /// Representation of a node in the generated sourcemap tree.
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct SourcemapNode {
+    /// The ID of the instance, which later messages refer to. Only included
+    /// with --stream.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Ref>,
+
     name: String,
     class_name: String,
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
     file_paths: Vec<PathBuf>,
 
+    /// The project file that defines this instance, if any. Only included
+    /// with --project-sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_file: Option<PathBuf>,
+
+    /// A JSON pointer to the node in `project_file` that defines this
+    /// instance, like `/tree/ReplicatedStorage`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_pointer: Option<String>,
+
     #[serde(skip_serializing_if = "Vec::is_empty")]
     children: Vec<SourcemapNode>,
 }
@@ -53,9 +77,130 @@ pub struct SourcemapCommand {
     #[clap(long)]
     pub include_non_scripts: bool,
 
+    /// Only include instances whose class name matches this glob, like
+    /// ModuleScript or *Script. Can be given more than once.
+    #[clap(long = "class", parse(try_from_str = Glob::new))]
+    pub classes: Vec<Glob>,
+
+    /// Only include instances whose dotted path matches this glob, like
+    /// ReplicatedStorage.Shared.*. Can be given more than once.
+    #[clap(long = "path", parse(try_from_str = Glob::new))]
+    pub paths: Vec<Glob>,
+
+    /// The format to write the sourcemap in. Valid values are nested and flat.
+    ///
+    /// The nested format is a JSON tree, as used by luau-lsp. The flat format
+    /// has one line per instance with its dotted path, class, and file paths,
+    /// separated by tabs.
+    #[clap(long, default_value = "nested")]
+    pub format: SourcemapFormat,
+
+    /// Whether to write absolute file paths instead of paths relative to the
+    /// project folder.
+    #[clap(long)]
+    pub absolute: bool,
+
+    /// Whether to include the project file and a JSON pointer to the node
+    /// that defines each instance described by a project file. In the flat
+    /// format, these are written as an extra file path like
+    /// default.project.json#/tree/ReplicatedStorage.
+    #[clap(long)]
+    pub project_sources: bool,
+
     /// Whether to automatically recreate a snapshot when any input files change.
     #[clap(long)]
     pub watch: bool,
+
+    /// Whether to keep running and stream changes to the sourcemap on stdout
+    /// as line-delimited JSON, instead of writing the whole sourcemap again.
+    ///
+    /// The full sourcemap is sent first, followed by messages describing each
+    /// change. Every instance is included in this mode. Sending
+    /// {"type":"full"} on stdin asks for the full sourcemap again, and
+    /// {"type":"exit"} or closing stdin stops Rojo.
+    #[clap(
+        long,
+        conflicts_with_all = &["output", "watch", "format", "classes", "paths", "include-non-scripts"]
+    )]
+    pub stream: bool,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
+}
+
+/// A message sent on stdout by `rojo sourcemap --stream`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum StreamMessage {
+    /// The whole sourcemap. Clients should discard anything they had before.
+    Full { root: Option<SourcemapNode> },
+
+    /// An instance and its descendants were added under `parent`. If a node
+    /// with the same ID already exists, it should be replaced.
+    Add { parent: Ref, node: SourcemapNode },
+
+    /// Instances, along with their descendants, were removed.
+    Remove { ids: Vec<Ref> },
+
+    /// An instance's name, class, or files changed. The project source is
+    /// only included with --project-sources, like in `SourcemapNode`.
+    #[serde(rename_all = "camelCase")]
+    Update {
+        id: Ref,
+        name: String,
+        class_name: String,
+        file_paths: Vec<PathBuf>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        project_file: Option<PathBuf>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        project_pointer: Option<String>,
+    },
+
+    /// A request on stdin couldn't be understood.
+    Error { message: String },
+}
+
+/// A request that `rojo sourcemap --stream` accepts on stdin.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum StreamRequest {
+    /// Asks for the full sourcemap to be sent again.
+    Full,
+
+    /// Asks Rojo to stop.
+    Exit,
+}
+
+/// The formats that `rojo sourcemap` can write.
+#[derive(Debug, Clone, Copy)]
+pub enum SourcemapFormat {
+    /// A JSON tree of instances.
+    Nested,
+
+    /// One line of tab-separated values per instance.
+    Flat,
+}
+
+impl FromStr for SourcemapFormat {
+    type Err = SourcemapFormatParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "nested" => Ok(SourcemapFormat::Nested),
+            "flat" => Ok(SourcemapFormat::Flat),
+            _ => Err(SourcemapFormatParseError {
+                attempted: source.to_owned(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid sourcemap format '{attempted}'. Valid values are: nested, flat")]
+pub struct SourcemapFormatParseError {
+    attempted: String,
 }
 
 impl SourcemapCommand {
@@ -64,18 +209,22 @@ impl SourcemapCommand {
 
         log::trace!("Constructing in-memory filesystem");
         let vfs = Vfs::new_default();
-        vfs.set_watch_enabled(self.watch);
+        vfs.set_watch_enabled(self.watch || self.stream);
 
-        let session = ServeSession::new(vfs, &project_path)?;
+        let session = ServeSession::new_with_context(
+            vfs,
+            &project_path,
+            self.project_options.instance_context(),
+        )?;
         let mut cursor = session.message_queue().cursor();
 
-        let filter = if self.include_non_scripts {
-            filter_nothing
-        } else {
-            filter_non_scripts
-        };
+        if self.stream {
+            let result = self.stream(&session);
+            forget(session);
+            return result;
+        }
 
-        write_sourcemap(&session, self.output.as_deref(), filter)?;
+        write_sourcemap(&session, self.output.as_deref(), &self)?;
 
         if self.watch {
             let rt = Runtime::new().unwrap();
@@ -85,7 +234,7 @@ impl SourcemapCommand {
                 let (new_cursor, _patch_set) = rt.block_on(receiver).unwrap();
                 cursor = new_cursor;
 
-                write_sourcemap(&session, self.output.as_deref(), filter)?;
+                write_sourcemap(&session, self.output.as_deref(), &self)?;
             }
         }
 
@@ -95,77 +244,783 @@ impl SourcemapCommand {
 
         Ok(())
     }
-}
 
-fn filter_nothing(_instance: &InstanceWithMeta) -> bool {
-    true
+    /// Sends the full sourcemap, then a message for every change to the tree,
+    /// until stdin is closed or asks us to stop.
+    fn stream(&self, session: &ServeSession) -> anyhow::Result<()> {
+        let (request_sender, mut requests) = mpsc::unbounded();
+
+        // Reading stdin blocks, so it gets its own thread. When stdin closes,
+        // the sender is dropped and the stream of requests ends.
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if request_sender.unbounded_send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let rt = Runtime::new().unwrap();
+
+        // The cursor is read before the tree so that no change can be missed.
+        // A change that lands in between is sent again, which clients handle
+        // by replacing nodes with the same ID.
+        let mut cursor = session.message_queue().cursor();
+        self.send_full(session)?;
+
+        loop {
+            let patches = session.message_queue().subscribe(cursor);
+
+            match rt.block_on(future::select(patches, requests.next())) {
+                Either::Left((result, _)) => {
+                    let (new_cursor, patch_sets) = result.unwrap();
+                    cursor = new_cursor;
+
+                    for patch_set in &patch_sets {
+                        self.send_patch_set(session, patch_set)?;
+                    }
+                }
+                Either::Right((Some(line), _)) => match serde_json::from_str(&line) {
+                    Ok(StreamRequest::Full) => self.send_full(session)?,
+                    Ok(StreamRequest::Exit) => return Ok(()),
+                    Err(err) => send_message(&StreamMessage::Error {
+                        message: format!("Invalid request: {}", err),
+                    })?,
+                },
+                Either::Right((None, _)) => return Ok(()),
+            }
+        }
+    }
+
+    fn send_full(&self, session: &ServeSession) -> anyhow::Result<()> {
+        let tree = session.tree();
+        let root = recurse_create_node(&tree, tree.get_root_id(), session.root_dir(), self);
+
+        send_message(&StreamMessage::Full { root })
+    }
+
+    fn send_patch_set(
+        &self,
+        session: &ServeSession,
+        patch_set: &AppliedPatchSet,
+    ) -> anyhow::Result<()> {
+        let tree = session.tree();
+
+        for message in self.patch_set_messages(&tree, session.root_dir(), patch_set) {
+            send_message(&message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates messages describing the changes in a patch set, based on the
+    /// tree as it is now.
+    fn patch_set_messages(
+        &self,
+        tree: &RojoTree,
+        project_dir: &Path,
+        patch_set: &AppliedPatchSet,
+    ) -> Vec<StreamMessage> {
+        let mut messages = Vec::new();
+
+        if !patch_set.removed.is_empty() {
+            messages.push(StreamMessage::Remove {
+                ids: patch_set.removed.clone(),
+            });
+        }
+
+        // Descendants of added instances are sent as part of their ancestor's
+        // node, so only the topmost added instances need messages.
+        let added: HashSet<Ref> = patch_set.added.iter().copied().collect();
+
+        for &id in &patch_set.added {
+            let instance = match tree.get_instance(id) {
+                Some(instance) => instance,
+                None => continue,
+            };
+
+            if added.contains(&instance.parent()) {
+                continue;
+            }
+
+            if let Some(node) = recurse_create_node(tree, id, project_dir, self) {
+                messages.push(StreamMessage::Add {
+                    parent: instance.parent(),
+                    node,
+                });
+            }
+        }
+
+        for update in &patch_set.updated {
+            let changed = update.changed_name.is_some()
+                || update.changed_class_name.is_some()
+                || update.changed_metadata.is_some();
+
+            if !changed {
+                continue;
+            }
+
+            if let Some(instance) = tree.get_instance(update.id) {
+                let (project_file, project_pointer) =
+                    match self.project_source(tree, &instance, project_dir) {
+                        Some((project_file, pointer)) => (Some(project_file), Some(pointer)),
+                        None => (None, None),
+                    };
+
+                messages.push(StreamMessage::Update {
+                    id: update.id,
+                    name: instance.name().to_owned(),
+                    class_name: instance.class_name().to_owned(),
+                    file_paths: self.file_paths(&instance, project_dir),
+                    project_file,
+                    project_pointer,
+                });
+            }
+        }
+
+        messages
+    }
+
+    /// Tells whether an instance should be in the sourcemap. In the nested
+    /// format, ancestors of these instances are included too.
+    fn includes(&self, tree: &RojoTree, instance: &InstanceWithMeta) -> bool {
+        if self.stream {
+            return true;
+        }
+
+        let class_name = instance.class_name();
+
+        if !self.include_non_scripts
+            && !matches!(class_name, "Script" | "LocalScript" | "ModuleScript")
+        {
+            return false;
+        }
+
+        if !self.classes.is_empty() && !self.classes.iter().any(|glob| glob.is_match(class_name)) {
+            return false;
+        }
+
+        if !self.paths.is_empty() {
+            let full_name = tree.get_full_name(instance.id());
+
+            if !self.paths.iter().any(|glob| glob.is_match(&full_name)) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn file_paths(&self, instance: &InstanceWithMeta, project_dir: &Path) -> Vec<PathBuf> {
+        instance
+            .metadata()
+            .relevant_paths
+            .iter()
+            // Not all paths listed as relevant are guaranteed to exist.
+            .filter(|path| path.is_file())
+            .map(|path| self.display_path(path, project_dir))
+            .collect()
+    }
+
+    fn display_path(&self, path: &Path, project_dir: &Path) -> PathBuf {
+        if self.absolute {
+            return path.to_path_buf();
+        }
+
+        // Files outside of the project folder, like a base project that the
+        // project extends from somewhere else, are left absolute.
+        path.strip_prefix(project_dir).unwrap_or(path).to_path_buf()
+    }
+
+    /// Returns the project file that defines an instance along with a JSON
+    /// pointer to its node in that file, if --project-sources was given.
+    fn project_source(
+        &self,
+        tree: &RojoTree,
+        instance: &InstanceWithMeta,
+        project_dir: &Path,
+    ) -> Option<(PathBuf, String)> {
+        if !self.project_sources {
+            return None;
+        }
+
+        let (project_path, pointer) = project_node_pointer(tree, instance)?;
+
+        Some((self.display_path(project_path, project_dir), pointer))
+    }
 }
 
-fn filter_non_scripts(instance: &InstanceWithMeta) -> bool {
-    matches!(
-        instance.class_name(),
-        "Script" | "LocalScript" | "ModuleScript"
-    )
+/// Finds the project file that defines an instance and a JSON pointer to the
+/// instance's node in it.
+///
+/// The root node of a project file is snapshotted with the project file as its
+/// path, while every other node remembers its project file and name. Walking
+/// up through ancestors from the same project file leads to a child of the
+/// project's root node, which gives the rest of the pointer.
+fn project_node_pointer<'a>(
+    tree: &'a RojoTree,
+    instance: &InstanceWithMeta<'a>,
+) -> Option<(&'a Path, String)> {
+    let project_path = match &instance.metadata().instigating_source {
+        Some(InstigatingSource::Path(path)) if Project::is_project_file(path) => {
+            return Some((path, "/tree".to_owned()));
+        }
+        Some(InstigatingSource::ProjectNode(path, _, _, _)) => path.as_path(),
+        _ => return None,
+    };
+
+    let mut names = vec![instance.name()];
+    let mut current = tree.get_instance(instance.parent());
+
+    while let Some(parent) = current {
+        match &parent.metadata().instigating_source {
+            Some(InstigatingSource::ProjectNode(path, _, _, _)) if path == project_path => {
+                names.push(parent.name());
+                current = tree.get_instance(parent.parent());
+            }
+            _ => break,
+        }
+    }
+
+    let mut pointer = "/tree".to_owned();
+
+    for name in names.into_iter().rev() {
+        pointer.push('/');
+        pointer.push_str(&escape_pointer(name));
+    }
+
+    Some((project_path, pointer))
 }
 
 fn recurse_create_node(
     tree: &RojoTree,
     referent: Ref,
     project_dir: &Path,
-    filter: fn(&InstanceWithMeta) -> bool,
+    options: &SourcemapCommand,
 ) -> Option<SourcemapNode> {
     let instance = tree.get_instance(referent).expect("instance did not exist");
 
     let mut children = Vec::new();
     for &child_id in instance.children() {
-        if let Some(child_node) = recurse_create_node(tree, child_id, project_dir, filter) {
+        if let Some(child_node) = recurse_create_node(tree, child_id, project_dir, options) {
             children.push(child_node);
         }
     }
 
     // If this object has no children and doesn't pass the filter, it doesn't
     // contain any information we're looking for.
-    if children.is_empty() && !filter(&instance) {
+    if children.is_empty() && !options.includes(tree, &instance) {
         return None;
     }
 
-    let file_paths = instance
-        .metadata()
-        .relevant_paths
-        .iter()
-        // Not all paths listed as relevant are guaranteed to exist.
-        .filter(|path| path.is_file())
-        .map(|path| path.strip_prefix(project_dir).expect(PATH_STRIP_FAILED_ERR))
-        .map(|path| path.to_path_buf())
-        .collect();
+    let (project_file, project_pointer) = match options.project_source(tree, &instance, project_dir)
+    {
+        Some((project_file, pointer)) => (Some(project_file), Some(pointer)),
+        None => (None, None),
+    };
 
     Some(SourcemapNode {
+        id: options.stream.then(|| referent),
         name: instance.name().to_string(),
         class_name: instance.class_name().to_string(),
-        file_paths,
+        file_paths: options.file_paths(&instance, project_dir),
+        project_file,
+        project_pointer,
         children,
     })
 }
 
+/// Writes a message to stdout as a single line of JSON.
+fn send_message(message: &StreamMessage) -> anyhow::Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    serde_json::to_writer(&mut stdout, message)?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+/// Writes one line for each instance that passes the filter, with its dotted
+/// path, class name, and file paths separated by tabs.
+fn write_flat(
+    writer: &mut dyn Write,
+    tree: &RojoTree,
+    project_dir: &Path,
+    options: &SourcemapCommand,
+) -> anyhow::Result<()> {
+    let mut to_visit = vec![tree.get_root_id()];
+
+    while let Some(id) = to_visit.pop() {
+        let instance = tree.get_instance(id).expect("instance did not exist");
+
+        if options.includes(tree, &instance) {
+            write!(
+                writer,
+                "{}\t{}",
+                tree.get_full_name(id),
+                instance.class_name()
+            )?;
+
+            for path in options.file_paths(&instance, project_dir) {
+                write!(writer, "\t{}", path.display())?;
+            }
+
+            if let Some((project_file, pointer)) =
+                options.project_source(tree, &instance, project_dir)
+            {
+                write!(writer, "\t{}#{}", project_file.display(), pointer)?;
+            }
+
+            writeln!(writer)?;
+        }
+
+        // Children are pushed in reverse so that they're written in order.
+        to_visit.extend(instance.children().iter().rev().copied());
+    }
+
+    Ok(())
+}
+
 fn write_sourcemap(
     session: &ServeSession,
     output: Option<&Path>,
-    filter: fn(&InstanceWithMeta) -> bool,
+    options: &SourcemapCommand,
 ) -> anyhow::Result<()> {
     let tree = session.tree();
+    let project_dir = session.root_dir();
 
-    let root_node = recurse_create_node(&tree, tree.get_root_id(), session.root_dir(), filter);
+    let write_to = |writer: &mut dyn Write| -> anyhow::Result<()> {
+        match options.format {
+            SourcemapFormat::Nested => {
+                let root_node =
+                    recurse_create_node(&tree, tree.get_root_id(), project_dir, options);
+                serde_json::to_writer(&mut *writer, &root_node)?;
+            }
+            SourcemapFormat::Flat => write_flat(writer, &tree, project_dir, options)?,
+        }
+
+        Ok(())
+    };
 
     if let Some(output_path) = output {
         let mut file = BufWriter::new(File::create(&output_path)?);
-        serde_json::to_writer(&mut file, &root_node)?;
+        write_to(&mut file)?;
         file.flush()?;
 
         println!("Created sourcemap at {}", output_path.display());
     } else {
-        let output = serde_json::to_string(&root_node)?;
-        println!("{}", output);
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        write_to(&mut stdout)?;
+
+        if let SourcemapFormat::Nested = options.format {
+            writeln!(stdout)?;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::{json, Value};
+    use tempfile::{tempdir, TempDir};
+
+    use crate::{
+        project::ProjectNode,
+        snapshot::{AppliedPatchUpdate, InstanceMetadata, InstanceSnapshot},
+    };
+
+    fn options(args: &[&str]) -> SourcemapCommand {
+        SourcemapCommand::parse_from(["sourcemap"].iter().chain(args))
+    }
+
+    /// Creates a script that was made from a file in `project_dir`, creating
+    /// the file too since only files that exist are listed.
+    fn script(project_dir: &Path, name: &str, class_name: &str, file: &str) -> InstanceSnapshot {
+        let path = project_dir.join(file);
+        fs_err::create_dir_all(path.parent().unwrap()).unwrap();
+        fs_err::write(&path, "").unwrap();
+
+        InstanceSnapshot::new()
+            .name(name)
+            .class_name(class_name)
+            .metadata(
+                InstanceMetadata::new()
+                    .instigating_source(path.as_path())
+                    .relevant_paths(vec![path]),
+            )
+    }
+
+    /// Creates a place with a few scripts in a temporary project folder.
+    fn place() -> (TempDir, RojoTree) {
+        let dir = tempdir().unwrap();
+        let project_dir = dir.path();
+
+        let shared = InstanceSnapshot::new()
+            .name("Shared")
+            .class_name("Folder")
+            .children(vec![
+                script(project_dir, "Util", "ModuleScript", "src/shared/Util.lua"),
+                InstanceSnapshot::new()
+                    .name("Config")
+                    .class_name("Configuration"),
+            ]);
+
+        let tree = RojoTree::new(
+            InstanceSnapshot::new()
+                .name("Game")
+                .class_name("DataModel")
+                .children(vec![
+                    InstanceSnapshot::new()
+                        .name("ReplicatedStorage")
+                        .class_name("ReplicatedStorage")
+                        .children(vec![shared]),
+                    InstanceSnapshot::new()
+                        .name("ServerScriptService")
+                        .class_name("ServerScriptService")
+                        .children(vec![script(
+                            project_dir,
+                            "Main",
+                            "Script",
+                            "src/server/Main.server.lua",
+                        )]),
+                ]),
+        );
+
+        (dir, tree)
+    }
+
+    fn flat(tree: &RojoTree, project_dir: &Path, options: &SourcemapCommand) -> String {
+        let mut output = Vec::new();
+        write_flat(&mut output, tree, project_dir, options).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    fn nested(tree: &RojoTree, project_dir: &Path, options: &SourcemapCommand) -> Value {
+        let root = recurse_create_node(tree, tree.get_root_id(), project_dir, options);
+
+        serde_json::to_value(root).unwrap()
+    }
+
+    #[test]
+    fn flat_lists_scripts() {
+        let (dir, tree) = place();
+        let output = flat(&tree, dir.path(), &options(&["--format", "flat"]));
+
+        assert_eq!(
+            output,
+            "ReplicatedStorage.Shared.Util\tModuleScript\tsrc/shared/Util.lua\n\
+             ServerScriptService.Main\tScript\tsrc/server/Main.server.lua\n"
+        );
+    }
+
+    #[test]
+    fn class_filter() {
+        let (dir, tree) = place();
+        let options = options(&[
+            "--format",
+            "flat",
+            "--include-non-scripts",
+            "--class",
+            "Config*",
+        ]);
+
+        assert_eq!(
+            flat(&tree, dir.path(), &options),
+            "ReplicatedStorage.Shared.Config\tConfiguration\n"
+        );
+    }
+
+    #[test]
+    fn path_filter_keeps_ancestors_in_nested_format() {
+        let (dir, tree) = place();
+        let output = nested(
+            &tree,
+            dir.path(),
+            &options(&["--path", "ReplicatedStorage.*"]),
+        );
+
+        assert_eq!(
+            output,
+            json!({
+                "name": "Game",
+                "className": "DataModel",
+                "children": [{
+                    "name": "ReplicatedStorage",
+                    "className": "ReplicatedStorage",
+                    "children": [{
+                        "name": "Shared",
+                        "className": "Folder",
+                        "children": [{
+                            "name": "Util",
+                            "className": "ModuleScript",
+                            "filePaths": ["src/shared/Util.lua"],
+                        }],
+                    }],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn absolute_paths() {
+        let (dir, tree) = place();
+        let options = options(&["--format", "flat", "--absolute", "--class", "Script"]);
+
+        assert_eq!(
+            flat(&tree, dir.path(), &options),
+            format!(
+                "ServerScriptService.Main\tScript\t{}\n",
+                dir.path().join("src/server/Main.server.lua").display()
+            )
+        );
+    }
+
+    /// Creates an instance described by a node in the given project file.
+    fn project_node(project_path: &Path, name: &str, class_name: &str) -> InstanceSnapshot {
+        InstanceSnapshot::new()
+            .name(name)
+            .class_name(class_name)
+            .metadata(
+                InstanceMetadata::new().instigating_source(InstigatingSource::ProjectNode(
+                    project_path.to_path_buf(),
+                    name.to_owned(),
+                    ProjectNode::default(),
+                    None,
+                )),
+            )
+    }
+
+    /// Creates a place from a project that includes a nested project in
+    /// `packages`.
+    fn project_place() -> RojoTree {
+        let project_path = Path::new("/project/default.project.json");
+        let packages_path = Path::new("/project/packages/default.project.json");
+
+        let packages = InstanceSnapshot::new()
+            .name("Packages")
+            .class_name("Folder")
+            .metadata(InstanceMetadata::new().instigating_source(packages_path))
+            .children(vec![project_node(packages_path, "Promise", "ModuleScript")]);
+
+        let util = InstanceSnapshot::new()
+            .name("Util")
+            .class_name("ModuleScript")
+            .metadata(
+                InstanceMetadata::new().instigating_source(Path::new("/project/src/Util.lua")),
+            );
+
+        RojoTree::new(
+            InstanceSnapshot::new()
+                .name("Game")
+                .class_name("DataModel")
+                .metadata(InstanceMetadata::new().instigating_source(project_path))
+                .children(vec![project_node(
+                    project_path,
+                    "ReplicatedStorage",
+                    "ReplicatedStorage",
+                )
+                .children(vec![
+                    project_node(project_path, "Net/Remotes", "Folder"),
+                    packages,
+                    util,
+                ])]),
+        )
+    }
+
+    fn pointer_for(tree: &RojoTree, id: Ref) -> Option<(PathBuf, String)> {
+        let instance = tree.get_instance(id).unwrap();
+
+        project_node_pointer(tree, &instance).map(|(path, pointer)| (path.to_path_buf(), pointer))
+    }
+
+    #[test]
+    fn project_node_pointers() {
+        let tree = project_place();
+        let project_path = PathBuf::from("/project/default.project.json");
+        let packages_path = PathBuf::from("/project/packages/default.project.json");
+
+        let replicated_storage = tree.find_by_full_name("ReplicatedStorage").unwrap();
+        let remotes = tree.get_instance(replicated_storage).unwrap().children()[0];
+        let promise = tree
+            .find_by_full_name("ReplicatedStorage.Packages.Promise")
+            .unwrap();
+
+        assert_eq!(
+            pointer_for(&tree, tree.get_root_id()),
+            Some((project_path.clone(), "/tree".to_owned()))
+        );
+        assert_eq!(
+            pointer_for(&tree, replicated_storage),
+            Some((project_path.clone(), "/tree/ReplicatedStorage".to_owned()))
+        );
+        assert_eq!(
+            pointer_for(&tree, remotes),
+            Some((
+                project_path,
+                "/tree/ReplicatedStorage/Net~1Remotes".to_owned()
+            ))
+        );
+        assert_eq!(
+            pointer_for(&tree, promise),
+            Some((packages_path, "/tree/Promise".to_owned()))
+        );
+    }
+
+    #[test]
+    fn instances_from_files_have_no_project_pointer() {
+        let tree = project_place();
+        let util = tree.find_by_full_name("ReplicatedStorage.Util").unwrap();
+
+        assert_eq!(pointer_for(&tree, util), None);
+    }
+
+    #[test]
+    fn flat_project_sources() {
+        let tree = project_place();
+        let options = options(&[
+            "--format",
+            "flat",
+            "--project-sources",
+            "--path",
+            "ReplicatedStorage.Packages.*",
+        ]);
+
+        assert_eq!(
+            flat(&tree, Path::new("/project"), &options),
+            "ReplicatedStorage.Packages.Promise\tModuleScript\t\
+             packages/default.project.json#/tree/Promise\n"
+        );
+    }
+
+    #[test]
+    fn nested_project_sources() {
+        let tree = project_place();
+        let options = options(&["--project-sources", "--path", "ReplicatedStorage.Util"]);
+        let output = nested(&tree, Path::new("/project"), &options);
+
+        assert_eq!(output["projectFile"], json!("default.project.json"));
+        assert_eq!(output["projectPointer"], json!("/tree"));
+
+        let replicated_storage = &output["children"][0];
+        assert_eq!(
+            replicated_storage["projectPointer"],
+            json!("/tree/ReplicatedStorage")
+        );
+
+        let util = &replicated_storage["children"][0];
+        assert_eq!(util["name"], json!("Util"));
+        assert!(util.get("projectFile").is_none());
+    }
+
+    fn stream_messages(
+        options: &SourcemapCommand,
+        tree: &RojoTree,
+        patch_set: &AppliedPatchSet,
+    ) -> Value {
+        let messages = options.patch_set_messages(tree, Path::new("/project"), patch_set);
+
+        serde_json::to_value(messages).unwrap()
+    }
+
+    #[test]
+    fn stream_patch_set_messages() {
+        let tree = project_place();
+        let options = options(&["--stream"]);
+
+        let removed = Ref::new();
+        let replicated_storage = tree.find_by_full_name("ReplicatedStorage").unwrap();
+        let packages = tree
+            .find_by_full_name("ReplicatedStorage.Packages")
+            .unwrap();
+        let promise = tree
+            .find_by_full_name("ReplicatedStorage.Packages.Promise")
+            .unwrap();
+
+        let mut renamed = AppliedPatchUpdate::new(replicated_storage);
+        renamed.changed_name = Some("ReplicatedStorage".to_owned());
+
+        // Property changes don't change anything in the sourcemap.
+        let mut changed_property = AppliedPatchUpdate::new(packages);
+        changed_property
+            .changed_properties
+            .insert("Tags".to_owned(), None);
+
+        let patch_set = AppliedPatchSet {
+            removed: vec![removed],
+            added: vec![packages, promise],
+            updated: vec![renamed, changed_property],
+        };
+
+        assert_eq!(
+            stream_messages(&options, &tree, &patch_set),
+            json!([
+                { "type": "remove", "ids": [removed] },
+                {
+                    "type": "add",
+                    "parent": replicated_storage,
+                    "node": {
+                        "id": packages,
+                        "name": "Packages",
+                        "className": "Folder",
+                        "children": [{
+                            "id": promise,
+                            "name": "Promise",
+                            "className": "ModuleScript",
+                        }],
+                    },
+                },
+                {
+                    "type": "update",
+                    "id": replicated_storage,
+                    "name": "ReplicatedStorage",
+                    "className": "ReplicatedStorage",
+                    "filePaths": [],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn stream_updates_include_project_sources() {
+        let tree = project_place();
+        let options = options(&["--stream", "--project-sources"]);
+
+        let promise = tree
+            .find_by_full_name("ReplicatedStorage.Packages.Promise")
+            .unwrap();
+
+        let mut renamed = AppliedPatchUpdate::new(promise);
+        renamed.changed_name = Some("Promise".to_owned());
+
+        let patch_set = AppliedPatchSet {
+            removed: Vec::new(),
+            added: Vec::new(),
+            updated: vec![renamed],
+        };
+
+        assert_eq!(
+            stream_messages(&options, &tree, &patch_set),
+            json!([{
+                "type": "update",
+                "id": promise,
+                "name": "Promise",
+                "className": "ModuleScript",
+                "filePaths": [],
+                "projectFile": "packages/default.project.json",
+                "projectPointer": "/tree/Promise",
+            }])
+        );
+    }
+}
@@ -0,0 +1,493 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use fs_err::{self as fs, File};
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Instance, WeakDom,
+};
+use serde::Serialize;
+
+use crate::{
+    project::{Project, ProjectNode},
+    resolution::UnresolvedValue,
+    snapshot_middleware::{convert_localization_json, split_properties, JsonModel},
+};
+
+use super::{build::read_model, resolve_path};
+
+/// Pairs of file name suffixes and the classes that Rojo creates from them, in
+/// the same order that Rojo's snapshot middleware checks them.
+///
+/// When writing an instance of one of these classes, the first matching suffix
+/// is used.
+const SINGLE_FILE_FORMATS: &[(&str, &str)] = &[
+    (".server.luau", "Script"),
+    (".server.lua", "Script"),
+    (".client.luau", "LocalScript"),
+    (".client.lua", "LocalScript"),
+    (".luau", "ModuleScript"),
+    (".lua", "ModuleScript"),
+    (".txt", "StringValue"),
+    (".csv", "LocalizationTable"),
+];
+
+/// Writes the instances in an existing place or model file into a project's
+/// files, following the layout that the project describes.
+///
+/// Every project node with a `$path` receives the instance at the same
+/// location in the input file. Existing files are overwritten, but files that
+/// don't correspond to any instance in the input are left alone.
+#[derive(Debug, Parser)]
+pub struct SyncbackCommand {
+    /// Path to the project to sync into. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// The place or model file to read instances from.
+    ///
+    /// Should end in .rbxl, .rbxlx, .rbxm, or .rbxmx.
+    #[clap(long, short)]
+    pub input: PathBuf,
+}
+
+impl SyncbackCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+        let project = Project::load_fuzzy(&project_path)?
+            .context("A project file is required to run 'rojo syncback'")?;
+
+        let dom = read_model(&self.input)?;
+        let root = dom.root();
+
+        // Place files are read with a root that stands in for the DataModel.
+        // Model files have their top-level instances as children of that root.
+        let root_id = if project.tree.class_name.as_deref() == Some("DataModel") {
+            root.referent()
+        } else {
+            match root.children() {
+                [child] => *child,
+                _ => bail!(
+                    "Rojo can only sync back model files with one top-level instance \
+                     into a project that isn't a place.\n\n\
+                     Check the model file at path {}",
+                    self.input.display()
+                ),
+            }
+        };
+
+        syncback_node(&dom, root_id, &project.tree, project.folder_location())?;
+
+        println!(
+            "Synced {} into project '{}'",
+            self.input.display(),
+            project.name
+        );
+
+        Ok(())
+    }
+}
+
+/// A `.meta.json` file describing the parts of an instance that don't fit into
+/// the instance's main file.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MetaFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    class_name: Option<String>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, UnresolvedValue>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, UnresolvedValue>,
+}
+
+impl MetaFile {
+    fn is_empty(&self) -> bool {
+        self.class_name.is_none() && self.properties.is_empty() && self.attributes.is_empty()
+    }
+
+    /// Writes the meta file to the given path, unless there's nothing to put
+    /// in it.
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)?;
+
+        Ok(())
+    }
+}
+
+/// Writes the instance paired with the given project node, then pairs up the
+/// node's children with the instance's children by name.
+fn syncback_node(
+    dom: &WeakDom,
+    id: Ref,
+    node: &ProjectNode,
+    project_folder: &Path,
+) -> anyhow::Result<()> {
+    let instance = dom.get_by_ref(id).expect("instance did not exist");
+
+    if let Some(path_node) = &node.path {
+        // Children defined by the project are written by the loop below, so
+        // they shouldn't also end up in the folder this node points to.
+        let skip = node.children.keys().map(|name| name.as_str()).collect();
+
+        // A node that sets both $className and $path must point to a Folder,
+        // so the class can't be written to the folder's meta file as well.
+        let dir_options = DirOptions {
+            skip,
+            has_class_name: node.class_name.is_some(),
+        };
+
+        write_instance(
+            dom,
+            id,
+            &project_folder.join(path_node.path()),
+            &dir_options,
+        )?;
+    }
+
+    for (child_name, child_node) in &node.children {
+        let child_id = instance.children().iter().copied().find(|&child_id| {
+            dom.get_by_ref(child_id)
+                .map(|child| &child.name == child_name)
+                .unwrap_or(false)
+        });
+
+        match child_id {
+            Some(child_id) => syncback_node(dom, child_id, child_node, project_folder)?,
+            None => log::warn!(
+                "Instance {} has no child named {}, so it was not synced back",
+                instance.name,
+                child_name
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Options for writing an instance as a directory.
+#[derive(Debug, Default)]
+struct DirOptions<'a> {
+    /// Names of children that are written somewhere else.
+    skip: HashSet<&'a str>,
+
+    /// Whether the project node pointing to the directory sets `$className`.
+    has_class_name: bool,
+}
+
+/// Writes an instance to the path that a project's `$path` points to, using
+/// whatever format that path implies.
+fn write_instance(
+    dom: &WeakDom,
+    id: Ref,
+    path: &Path,
+    dir_options: &DirOptions,
+) -> anyhow::Result<()> {
+    let instance = dom.get_by_ref(id).expect("instance did not exist");
+
+    if path.is_dir() || path.extension().is_none() {
+        return write_dir(dom, id, path, dir_options);
+    }
+
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("Path had an invalid file name: {}", path.display()))?;
+
+    if Project::is_project_file(path) {
+        let project = Project::load_fuzzy(path)?
+            .with_context(|| format!("Could not find project file {}", path.display()))?;
+
+        return syncback_node(dom, id, &project.tree, project.folder_location());
+    }
+
+    if file_name.ends_with(".model.json") {
+        return write_json_model(dom, id, path);
+    }
+
+    if file_name.ends_with(".rbxm") || file_name.ends_with(".rbxmx") {
+        return write_binary_or_xml_model(dom, id, path);
+    }
+
+    if let Some((_, class_name)) = find_single_file_format(file_name) {
+        if instance.class != class_name {
+            log::warn!(
+                "Instance {} is a {}, but {} will be built as a {}",
+                instance.name,
+                instance.class,
+                path.display(),
+                class_name
+            );
+        }
+
+        if !instance.children().is_empty() {
+            log::warn!(
+                "Instance {} has children, but {} cannot contain them. \
+                 Its children were not synced back.",
+                instance.name,
+                path.display()
+            );
+        }
+
+        return write_single_file(instance, path);
+    }
+
+    bail!(
+        "Rojo does not know how to write instance {} to the path {}",
+        instance.name,
+        path.display()
+    );
+}
+
+/// Writes an instance as a directory, with one entry per child.
+fn write_dir(dom: &WeakDom, id: Ref, path: &Path, options: &DirOptions) -> anyhow::Result<()> {
+    let instance = dom.get_by_ref(id).expect("instance did not exist");
+
+    fs::create_dir_all(path)?;
+
+    let init_suffix = find_init_suffix(&instance.class);
+
+    // Without an init file, the main property of an instance like a
+    // StringValue is kept in the meta file instead.
+    let mut meta = meta_for(instance, init_suffix.is_some());
+
+    match init_suffix {
+        Some(suffix) => {
+            fs::write(
+                path.join(format!("init{}", suffix)),
+                encode_main_property(instance)?,
+            )?;
+        }
+        None => {
+            if instance.class != "Folder" && !options.has_class_name {
+                meta.class_name = Some(instance.class.clone());
+            }
+        }
+    }
+
+    meta.write(&path.join("init.meta.json"))?;
+
+    let mut seen_names = HashSet::new();
+
+    for &child_id in instance.children() {
+        let child = dom.get_by_ref(child_id).expect("instance did not exist");
+
+        if options.skip.contains(child.name.as_str()) {
+            continue;
+        }
+
+        if !is_valid_file_name(&child.name) {
+            log::warn!(
+                "Instance {} in {} has a name that can't be used as a file name, \
+                 so it was not synced back",
+                child.name,
+                path.display()
+            );
+            continue;
+        }
+
+        if !seen_names.insert(child.name.as_str()) {
+            log::warn!(
+                "There is more than one instance named {} in {}. \
+                 Only the first was synced back.",
+                child.name,
+                path.display()
+            );
+            continue;
+        }
+
+        write_child(dom, child_id, path)?;
+    }
+
+    Ok(())
+}
+
+/// Picks the format for an instance found inside a directory and writes it,
+/// naming the file or directory after the instance.
+fn write_child(dom: &WeakDom, id: Ref, parent_path: &Path) -> anyhow::Result<()> {
+    let instance = dom.get_by_ref(id).expect("instance did not exist");
+    let name = &instance.name;
+    let has_children = !instance.children().is_empty();
+
+    if let Some(suffix) = find_class_suffix(&instance.class) {
+        if !has_children {
+            return write_single_file(instance, &parent_path.join(format!("{}{}", name, suffix)));
+        }
+
+        if find_init_suffix(&instance.class).is_some() {
+            return write_dir(dom, id, &parent_path.join(name), &DirOptions::default());
+        }
+    }
+
+    if instance.class == "Folder" {
+        return write_dir(dom, id, &parent_path.join(name), &DirOptions::default());
+    }
+
+    // JSON models can't express references between instances, so anything
+    // containing them is stored in a binary model instead.
+    if contains_refs(dom, id) {
+        write_binary_or_xml_model(dom, id, &parent_path.join(format!("{}.rbxm", name)))
+    } else {
+        write_json_model(dom, id, &parent_path.join(format!("{}.model.json", name)))
+    }
+}
+
+/// Writes an instance whose main property is stored as the contents of a file,
+/// like a script, along with an adjacent meta file for anything else.
+fn write_single_file(instance: &Instance, path: &Path) -> anyhow::Result<()> {
+    fs::write(path, encode_main_property(instance)?)?;
+
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap();
+    let (suffix, _) = find_single_file_format(file_name).unwrap();
+    let stem = &file_name[..file_name.len() - suffix.len()];
+
+    meta_for(instance, true).write(&path.with_file_name(format!("{}.meta.json", stem)))
+}
+
+fn write_json_model(dom: &WeakDom, id: Ref, path: &Path) -> anyhow::Result<()> {
+    let mut model = JsonModel::from_instance(dom, id);
+
+    // The name of the top-level instance comes from the file name.
+    model.name = None;
+
+    let contents = serde_json::to_string_pretty(&model)?;
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+fn write_binary_or_xml_model(dom: &WeakDom, id: Ref, path: &Path) -> anyhow::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+
+    if path.extension().and_then(|extension| extension.to_str()) == Some("rbxmx") {
+        let options = rbx_xml::EncodeOptions::new()
+            .property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown);
+
+        rbx_xml::to_writer(&mut file, dom, &[id], options)?;
+    } else {
+        rbx_binary::to_writer(&mut file, dom, &[id])?;
+    }
+
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Creates the meta file for an instance, containing every property that isn't
+/// already stored in the instance's main file, if it has one.
+fn meta_for(instance: &Instance, has_main_file: bool) -> MetaFile {
+    let (mut properties, attributes) = split_properties(instance);
+
+    if has_main_file {
+        if let Some(main_property) = main_property(&instance.class) {
+            properties.remove(main_property);
+        }
+    }
+
+    MetaFile {
+        class_name: None,
+        properties,
+        attributes,
+    }
+}
+
+/// The property that is stored as the contents of the file for instances of
+/// the given class.
+fn main_property(class_name: &str) -> Option<&'static str> {
+    match class_name {
+        "Script" | "LocalScript" | "ModuleScript" => Some("Source"),
+        "StringValue" => Some("Value"),
+        "LocalizationTable" => Some("Contents"),
+        _ => None,
+    }
+}
+
+fn encode_main_property(instance: &Instance) -> anyhow::Result<Vec<u8>> {
+    let property = main_property(&instance.class)
+        .with_context(|| format!("Class {} cannot be stored in a file", instance.class))?;
+
+    let value = match instance.properties.get(property) {
+        Some(Variant::String(value)) => value.as_str(),
+        Some(_) => bail!(
+            "Property {}.{} of instance {} was not a string",
+            instance.class,
+            property,
+            instance.name
+        ),
+        None => "",
+    };
+
+    if instance.class == "LocalizationTable" {
+        convert_localization_json(value).with_context(|| {
+            format!(
+                "Could not convert LocalizationTable {} to CSV",
+                instance.name
+            )
+        })
+    } else {
+        Ok(value.as_bytes().to_vec())
+    }
+}
+
+fn find_single_file_format(file_name: &str) -> Option<(&'static str, &'static str)> {
+    SINGLE_FILE_FORMATS
+        .iter()
+        .copied()
+        .find(|(suffix, _)| file_name.ends_with(suffix))
+}
+
+fn find_class_suffix(class_name: &str) -> Option<&'static str> {
+    SINGLE_FILE_FORMATS
+        .iter()
+        .find(|(_, class)| *class == class_name)
+        .map(|(suffix, _)| *suffix)
+}
+
+/// The suffix of the `init` file that turns a directory into an instance of
+/// the given class, if there is one.
+fn find_init_suffix(class_name: &str) -> Option<&'static str> {
+    match class_name {
+        // There is no init.txt file.
+        "StringValue" => None,
+        _ => find_class_suffix(class_name),
+    }
+}
+
+fn contains_refs(dom: &WeakDom, id: Ref) -> bool {
+    let instance = dom.get_by_ref(id).expect("instance did not exist");
+
+    let has_refs = instance.properties.values().any(|value| match value {
+        Variant::Ref(referent) => referent.is_some(),
+        _ => false,
+    });
+
+    has_refs
+        || instance
+            .children()
+            .iter()
+            .any(|&child_id| contains_refs(dom, child_id))
+}
+
+/// Tells whether Rojo can represent an instance with the given name as a file,
+/// and read it back with the same name.
+fn is_valid_file_name(name: &str) -> bool {
+    if name.is_empty() || name == "." || name == ".." || name == "init" {
+        return false;
+    }
+
+    !name.chars().any(|char| {
+        matches!(char, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || char.is_control()
+    })
+}
@@ -0,0 +1,391 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    io::{BufWriter, Write},
+    mem::forget,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use fs_err::File;
+use memofs::Vfs;
+use rbx_dom_weak::types::Ref;
+use tokio::runtime::Runtime;
+
+use crate::{
+    serve_session::ServeSession,
+    snapshot::{InstigatingSource, RojoTree},
+};
+
+use super::{resolve_path, ProjectOptions};
+
+/// Words that can't be used as property names in Luau without quoting them.
+const LUAU_KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Generates a Luau definition file describing the shape of the project's
+/// instance tree, so that indexing into it can be type checked.
+///
+/// The file is meant to be loaded as a definitions file by a language server,
+/// like luau-lsp's --definitions option. The root instance is declared as the
+/// class Rojo, and scripts opt into it with `local game = game :: Rojo`, since
+/// redeclaring Roblox's own globals conflicts with their built-in definitions.
+///
+/// ModuleScripts are typed as ModuleScript. Definition files can't call
+/// require, so the types of what modules return are left to the language
+/// server, which can find them with `rojo sourcemap`.
+#[derive(Debug, Parser)]
+pub struct TypesCommand {
+    /// Path to the project to generate types for. Defaults to the current
+    /// directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// Where to output the definitions. Omit this to use stdout instead of
+    /// writing to a file.
+    ///
+    /// Should end in .d.luau.
+    #[clap(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// Whether to automatically regenerate the definitions when any input
+    /// files change.
+    #[clap(long)]
+    pub watch: bool,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
+}
+
+impl TypesCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+
+        log::trace!("Constructing in-memory filesystem");
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(self.watch);
+
+        let session = ServeSession::new_with_context(
+            vfs,
+            &project_path,
+            self.project_options.instance_context(),
+        )?;
+        let mut cursor = session.message_queue().cursor();
+
+        write_types(&session, self.output.as_deref())?;
+
+        if self.watch {
+            let rt = Runtime::new().unwrap();
+
+            loop {
+                let receiver = session.message_queue().subscribe(cursor);
+                let (new_cursor, _patch_set) = rt.block_on(receiver).unwrap();
+                cursor = new_cursor;
+
+                write_types(&session, self.output.as_deref())?;
+            }
+        }
+
+        // Avoid dropping ServeSession: it's potentially VERY expensive to drop
+        // and we're about to exit anyways.
+        forget(session);
+
+        Ok(())
+    }
+}
+
+fn write_types(session: &ServeSession, output: Option<&Path>) -> anyhow::Result<()> {
+    let tree = session.tree();
+    let definitions = generate_types(&tree, session.root_dir());
+
+    if let Some(output_path) = output {
+        let mut file = BufWriter::new(File::create(output_path)?);
+        file.write_all(definitions.as_bytes())?;
+        file.flush()?;
+
+        println!("Created type definitions at {}", output_path.display());
+    } else {
+        print!("{}", definitions);
+    }
+
+    Ok(())
+}
+
+/// Generates Luau declarations for the tree.
+///
+/// Every instance with children gets a class extending its real class, with a
+/// property for each child. Instances without children are typed as their
+/// class directly.
+fn generate_types(tree: &RojoTree, project_dir: &Path) -> String {
+    let mut generator = TypesGenerator {
+        tree,
+        project_dir,
+        used_names: HashSet::new(),
+        output: String::new(),
+    };
+
+    writeln!(
+        generator.output,
+        "-- This file was generated by `rojo types`. Changes to it will be lost.\n"
+    )
+    .unwrap();
+
+    generator.declare(tree.get_root_id(), "Rojo".to_owned());
+
+    generator.output
+}
+
+struct TypesGenerator<'a> {
+    tree: &'a RojoTree,
+    project_dir: &'a Path,
+    used_names: HashSet<String>,
+    output: String,
+}
+
+impl TypesGenerator<'_> {
+    /// Declares the types of an instance and its descendants, returning the
+    /// name of the type that describes the instance.
+    ///
+    /// Classes are declared after the classes of their children, so every
+    /// type is declared before it's used.
+    fn declare(&mut self, id: Ref, name_hint: String) -> String {
+        let instance = self.tree.get_instance(id).unwrap();
+
+        if instance.children().is_empty() {
+            return instance.class_name().to_owned();
+        }
+
+        let type_name = self.unique_name(name_hint);
+
+        // A property can only describe one child, so children that share a
+        // name with a sibling are typed as a plain Instance.
+        let mut name_counts: HashMap<&str, usize> = HashMap::new();
+        for &child_id in instance.children() {
+            let child = self.tree.get_instance(child_id).unwrap();
+            *name_counts.entry(child.name()).or_default() += 1;
+        }
+
+        let mut fields = Vec::new();
+        let mut declared_names = HashSet::new();
+
+        for &child_id in instance.children() {
+            let child = self.tree.get_instance(child_id).unwrap();
+            let name = child.name();
+
+            if name_counts[name] > 1 {
+                if declared_names.insert(name) {
+                    fields.push((name, "Instance".to_owned(), None));
+                }
+                continue;
+            }
+
+            let child_type = self.declare(child_id, format!("{}_{}", type_name, identifier(name)));
+
+            let source_path = match &child.metadata().instigating_source {
+                Some(InstigatingSource::Path(path)) => Some(self.display_path(path)),
+                _ => None,
+            };
+
+            fields.push((name, child_type, source_path));
+        }
+
+        writeln!(
+            self.output,
+            "declare class {} extends {}",
+            type_name,
+            instance.class_name()
+        )
+        .unwrap();
+
+        for (name, field_type, source_path) in fields {
+            write!(self.output, "\t{}: {}", property_key(name), field_type).unwrap();
+
+            if let Some(source_path) = source_path {
+                write!(self.output, " -- {}", source_path).unwrap();
+            }
+
+            writeln!(self.output).unwrap();
+        }
+
+        writeln!(self.output, "end\n").unwrap();
+
+        type_name
+    }
+
+    /// Picks a type name based on `hint` that hasn't been used yet.
+    fn unique_name(&mut self, hint: String) -> String {
+        let mut name = hint.clone();
+        let mut suffix = 2;
+
+        while self.used_names.contains(&name) {
+            name = format!("{}{}", hint, suffix);
+            suffix += 1;
+        }
+
+        self.used_names.insert(name.clone());
+        name
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        let path = path.strip_prefix(self.project_dir).unwrap_or(path);
+        path.to_string_lossy().replace('\\', "/")
+    }
+}
+
+/// Turns an instance name into something usable as part of a Luau identifier.
+fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Formats an instance name as a property in a class declaration, quoting it
+/// if it isn't a valid identifier.
+fn property_key(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !LUAU_KEYWORDS.contains(&name);
+
+    if is_identifier {
+        return name.to_owned();
+    }
+
+    let mut key = String::from("[\"");
+
+    for c in name.chars() {
+        match c {
+            '"' | '\\' => {
+                key.push('\\');
+                key.push(c);
+            }
+            '\n' => key.push_str("\\n"),
+            _ => key.push(c),
+        }
+    }
+
+    key.push_str("\"]");
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::snapshot::{InstanceMetadata, InstanceSnapshot};
+
+    fn module(name: &str) -> InstanceSnapshot {
+        InstanceSnapshot::new()
+            .name(name)
+            .class_name("ModuleScript")
+    }
+
+    #[test]
+    fn place_types() {
+        let util = module("Util").metadata(
+            InstanceMetadata::new().instigating_source(Path::new("/project/src/shared/Util.lua")),
+        );
+
+        let shared = InstanceSnapshot::new()
+            .name("Shared")
+            .class_name("Folder")
+            .children(vec![
+                util,
+                module("end"),
+                InstanceSnapshot::new()
+                    .name("Duplicate")
+                    .class_name("Folder"),
+                module("Duplicate"),
+            ]);
+
+        let replicated_storage = InstanceSnapshot::new()
+            .name("ReplicatedStorage")
+            .class_name("ReplicatedStorage")
+            .children(vec![shared]);
+
+        let tree = RojoTree::new(
+            InstanceSnapshot::new()
+                .name("Game")
+                .class_name("DataModel")
+                .children(vec![
+                    replicated_storage,
+                    InstanceSnapshot::new()
+                        .name("Workspace")
+                        .class_name("Workspace"),
+                ]),
+        );
+
+        let expected = "\
+-- This file was generated by `rojo types`. Changes to it will be lost.
+
+declare class Rojo_ReplicatedStorage_Shared extends Folder
+\tUtil: ModuleScript -- src/shared/Util.lua
+\t[\"end\"]: ModuleScript
+\tDuplicate: Instance
+end
+
+declare class Rojo_ReplicatedStorage extends ReplicatedStorage
+\tShared: Rojo_ReplicatedStorage_Shared
+end
+
+declare class Rojo extends DataModel
+\tReplicatedStorage: Rojo_ReplicatedStorage
+\tWorkspace: Workspace
+end
+
+";
+
+        assert_eq!(generate_types(&tree, Path::new("/project")), expected);
+    }
+
+    #[test]
+    fn model_types() {
+        let tree = RojoTree::new(
+            InstanceSnapshot::new()
+                .name("Model")
+                .class_name("Folder")
+                .children(vec![module("Main")]),
+        );
+
+        let expected = "\
+-- This file was generated by `rojo types`. Changes to it will be lost.
+
+declare class Rojo extends Folder
+\tMain: ModuleScript
+end
+
+";
+
+        assert_eq!(generate_types(&tree, Path::new("/project")), expected);
+    }
+
+    #[test]
+    fn type_names_are_unique() {
+        let tree = RojoTree::new(InstanceSnapshot::new());
+        let mut generator = TypesGenerator {
+            tree: &tree,
+            project_dir: Path::new("/project"),
+            used_names: HashSet::new(),
+            output: String::new(),
+        };
+
+        assert_eq!(generator.unique_name("Rojo_A_B".to_owned()), "Rojo_A_B");
+        assert_eq!(generator.unique_name("Rojo_A_B".to_owned()), "Rojo_A_B2");
+        assert_eq!(generator.unique_name("Rojo_A_B".to_owned()), "Rojo_A_B3");
+    }
+
+    #[test]
+    fn property_keys() {
+        assert_eq!(property_key("Util"), "Util");
+        assert_eq!(property_key("_private2"), "_private2");
+        assert_eq!(property_key("2D"), "[\"2D\"]");
+        assert_eq!(property_key("Hello World"), "[\"Hello World\"]");
+        assert_eq!(property_key("return"), "[\"return\"]");
+        assert_eq!(property_key("say \"hi\""), "[\"say \\\"hi\\\"\"]");
+        assert_eq!(identifier("Hello World!"), "Hello_World_");
+    }
+}
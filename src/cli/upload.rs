@@ -11,7 +11,7 @@ use reqwest::{
 
 use crate::{auth_cookie::get_auth_cookie, serve_session::ServeSession};
 
-use super::resolve_path;
+use super::{resolve_path, ProjectOptions};
 
 /// Builds the project and uploads it to Roblox.
 #[derive(Debug, Parser)]
@@ -35,6 +35,9 @@ pub struct UploadCommand {
     /// Asset ID to upload to.
     #[clap(long = "asset_id")]
     pub asset_id: u64,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
 }
 
 impl UploadCommand {
@@ -43,7 +46,11 @@ impl UploadCommand {
 
         let vfs = Vfs::new_default();
 
-        let session = ServeSession::new(vfs, project_path)?;
+        let session = ServeSession::new_with_context(
+            vfs,
+            project_path,
+            self.project_options.instance_context(),
+        )?;
 
         let tree = session.tree();
         let inner_tree = tree.inner();
@@ -0,0 +1,844 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use clap::Parser;
+use memofs::{IoResultExt, Vfs};
+use serde::Serialize;
+use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
+
+use crate::{
+    project::{PathNode, Project, ProjectNode},
+    snapshot::{InstanceContext, PathIgnoreRule, Transformer, TransformerRule},
+    snapshot_middleware::{
+        dir_meta_paths, get_init_path, get_transformer, infer_class_name, snapshot_from_vfs,
+        DirectoryMetadata,
+    },
+};
+
+use super::{
+    display_path, escape_pointer, resolve_path, GlobalOptions, ProjectOptions, ReportFormat,
+};
+
+/// Checks a project for problems, reporting all of them at once instead of
+/// stopping at the first one.
+#[derive(Debug, Parser)]
+pub struct ValidateCommand {
+    /// Path to the project to validate. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+
+    /// The format to report problems in, 'text' or 'json'. Defaults to text.
+    #[clap(long, default_value = "text")]
+    pub format: ReportFormat,
+
+    #[clap(flatten)]
+    pub project_options: ProjectOptions,
+}
+
+impl ValidateCommand {
+    pub fn run(self, global: GlobalOptions) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let project_path = if vfs.metadata(&project_path)?.is_dir() {
+            Project::default_path(&project_path)
+        } else {
+            project_path.into_owned()
+        };
+
+        let mut validator = Validator::new(&vfs);
+        validator.validate_project_file(&self.project_options.instance_context(), &project_path);
+
+        let diagnostics = validator.diagnostics;
+
+        match self.format {
+            ReportFormat::Text => print_diagnostics(&diagnostics, global.color.into())?,
+            ReportFormat::Json => {
+                let output = DiagnosticsOutput {
+                    diagnostics: &diagnostics,
+                };
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+        }
+
+        let error_count = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count();
+
+        if error_count > 0 {
+            bail!("Found {} error(s) in project", error_count);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single problem found in a project.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Diagnostic {
+    severity: Severity,
+
+    /// The file that the problem was found in.
+    path: PathBuf,
+
+    /// A JSON pointer to the problematic value, if the problem is inside of a
+    /// JSON file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pointer: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+
+    message: String,
+}
+
+#[derive(Serialize)]
+struct DiagnosticsOutput<'a> {
+    diagnostics: &'a [Diagnostic],
+}
+
+/// Walks a project in the same order that snapshotting would, but records
+/// every problem it runs into instead of bailing on the first one.
+struct Validator<'a> {
+    vfs: &'a Vfs,
+    diagnostics: Vec<Diagnostic>,
+
+    /// Project files that have already been checked, which keeps projects that
+    /// refer to each other from being checked forever.
+    visited_projects: HashSet<PathBuf>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(vfs: &'a Vfs) -> Self {
+        Self {
+            vfs,
+            diagnostics: Vec::new(),
+            visited_projects: HashSet::new(),
+        }
+    }
+
+    /// Checks the project file at the given path, returning the class name of
+    /// its root instance if it could be figured out.
+    fn validate_project_file(&mut self, context: &InstanceContext, path: &Path) -> Option<String> {
+        if !self.visited_projects.insert(path.to_path_buf()) {
+            return None;
+        }
+
+        let contents = match self.vfs.read(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                self.report_error(path, None, &err.into());
+                return None;
+            }
+        };
+
+        let mut project =
+            match Project::load_from_slice_with_variables(&contents, path, &context.variables) {
+                Ok(project) => project,
+                Err(err) => {
+                    self.report_error(path, None, &err.into());
+                    return None;
+                }
+            };
+
+        // Like snapshotting, the profile is applied to any project that
+        // defines it, but only the root project has to.
+        if let Some(profile) = &context.profile {
+            let is_root = self.visited_projects.len() == 1;
+
+            if !project.apply_profile(profile) && is_root {
+                let available: Vec<&str> = project.profiles.keys().map(String::as_str).collect();
+
+                self.push(
+                    Severity::Error,
+                    path,
+                    None,
+                    format!(
+                        "There's no profile named '{}'. The project's profiles are: {}",
+                        profile,
+                        available.join(", ")
+                    ),
+                );
+            }
+        }
+
+        let mut context = context.clone();
+
+        let path_ignore_rules = project.glob_ignore_paths.iter().map(|glob| PathIgnoreRule {
+            glob: glob.clone(),
+            base_path: project.folder_location().to_path_buf(),
+        });
+
+        context.add_path_ignore_rules(path_ignore_rules);
+
+        for (index, rule) in project.transformer_rules.iter().enumerate() {
+            if let Transformer::Other(name) = Transformer::from_str(&rule.transformer_name) {
+                self.push(
+                    Severity::Error,
+                    path,
+                    Some(format!("/transformerRules/{}/use", index)),
+                    format!("Unknown rojo type: {}", name),
+                );
+            }
+        }
+
+        let transformer_rules = project
+            .transformer_rules
+            .iter()
+            .map(|rule| TransformerRule {
+                pattern: rule.pattern.clone(),
+                transformer_name: rule.transformer_name.clone(),
+                base_path: project.folder_location().to_path_buf(),
+            });
+
+        context.add_transformer_rules(transformer_rules);
+
+        self.validate_node(&context, path, &project.name, &project.tree, "/tree", None)
+    }
+
+    /// Checks a node from a project file and all of its children, returning
+    /// the node's class name if it could be figured out.
+    fn validate_node(
+        &mut self,
+        context: &InstanceContext,
+        project_path: &Path,
+        instance_name: &str,
+        node: &ProjectNode,
+        pointer: &str,
+        parent_class: Option<&str>,
+    ) -> Option<String> {
+        let mut class_name_from_path = None;
+
+        if let Some(path_node) = &node.path {
+            let path = path_node.path();
+            let full_path = if path.is_relative() {
+                project_path.parent().unwrap().join(path)
+            } else {
+                path.to_path_buf()
+            };
+
+            match self.vfs.metadata(&full_path).with_not_found() {
+                Ok(Some(meta)) => {
+                    class_name_from_path = if meta.is_dir() {
+                        self.validate_dir(context, &full_path)
+                    } else {
+                        self.validate_file(context, &full_path)
+                    };
+                }
+                Ok(None) => {
+                    if let PathNode::Required(_) = path_node {
+                        self.push(
+                            Severity::Error,
+                            project_path,
+                            Some(format!("{}/$path", pointer)),
+                            format!(
+                                "$path refers to a file that does not exist: {}",
+                                path.display()
+                            ),
+                        );
+                    }
+                }
+                Err(err) => self.report_error(&full_path, None, &err.into()),
+            }
+        }
+
+        if let Some(class_name) = &node.class_name {
+            if !rbx_reflection_database::get()
+                .classes
+                .contains_key(class_name.as_str())
+            {
+                self.push(
+                    Severity::Warning,
+                    project_path,
+                    Some(format!("{}/$className", pointer)),
+                    format!("Unknown class name: {}", class_name),
+                );
+            }
+        }
+
+        let class_name_from_inference =
+            infer_class_name(instance_name, parent_class).map(|name| name.into_owned());
+
+        let class_name = match (&node.class_name, class_name_from_path) {
+            (Some(project), None) => Some(project.clone()),
+            (Some(project), Some(path)) => {
+                if path != "Folder" {
+                    self.push(
+                        Severity::Error,
+                        project_path,
+                        Some(format!("{}/$className", pointer)),
+                        format!(
+                            "$className is set to \"{}\", but $path refers to a {}. \
+                             If $className and $path are both set, $path must refer to a Folder.",
+                            project, path
+                        ),
+                    );
+                }
+
+                Some(project.clone())
+            }
+            (None, Some(path)) => match class_name_from_inference {
+                Some(inference) if path == "Folder" => Some(inference),
+                _ => Some(path),
+            },
+            (None, None) => {
+                if class_name_from_inference.is_none() && node.path.is_none() {
+                    self.push(
+                        Severity::Error,
+                        project_path,
+                        Some(pointer.to_owned()),
+                        format!(
+                            "Instance \"{}\" is missing some required information. \
+                             It must have a $className, a $path, or be a known service.",
+                            instance_name
+                        ),
+                    );
+                }
+
+                class_name_from_inference
+            }
+        };
+
+        let mut property_names: Vec<_> = node.properties.keys().collect();
+        property_names.sort();
+
+        for key in property_names {
+            let property_pointer = format!("{}/$properties/{}", pointer, escape_pointer(key));
+
+            if matches!(key.as_str(), "Name" | "Parent") {
+                self.push(
+                    Severity::Warning,
+                    project_path,
+                    Some(property_pointer),
+                    format!(
+                        "Property '{}' cannot be set manually and will be ignored",
+                        key
+                    ),
+                );
+                continue;
+            }
+
+            // Without a class name, there's nothing to resolve against. Any
+            // reason for that has already been reported.
+            if let Some(class_name) = &class_name {
+                let unresolved = node.properties[key].clone();
+
+                if let Err(err) = unresolved.resolve(class_name, key) {
+                    self.report_error(project_path, Some(property_pointer), &err);
+                }
+            }
+        }
+
+        let mut attribute_names: Vec<_> = node.attributes.keys().collect();
+        attribute_names.sort();
+
+        for key in attribute_names {
+            let unresolved = node.attributes[key].clone();
+
+            if let Err(err) = unresolved.resolve_unambiguous() {
+                let attribute_pointer = format!("{}/$attributes/{}", pointer, escape_pointer(key));
+                self.report_error(project_path, Some(attribute_pointer), &err);
+            }
+        }
+
+        for (child_name, child_node) in &node.children {
+            let child_pointer = format!("{}/{}", pointer, escape_pointer(child_name));
+
+            if child_name.starts_with('$') {
+                self.push(
+                    Severity::Warning,
+                    project_path,
+                    Some(child_pointer.clone()),
+                    format!(
+                        "Keys starting with '$' are reserved by Rojo. The key '{}' should be renamed.",
+                        child_name
+                    ),
+                );
+            }
+
+            self.validate_node(
+                context,
+                project_path,
+                child_name,
+                child_node,
+                &child_pointer,
+                class_name.as_deref(),
+            );
+        }
+
+        class_name
+    }
+
+    /// Checks a directory and everything inside of it, returning the class
+    /// name of the instance it turns into if it could be figured out.
+    fn validate_dir(&mut self, context: &InstanceContext, path: &Path) -> Option<String> {
+        let init_path = match get_init_path(self.vfs, path) {
+            Ok(init_path) => init_path,
+            Err(err) => {
+                self.report_error(path, None, &err);
+                return None;
+            }
+        };
+
+        let mut class_name = Some("Folder".to_owned());
+
+        if let Some(init_path) = &init_path {
+            class_name = match get_transformer(context, init_path) {
+                // Directories with a project file in them turn into that
+                // project, and nothing else in them is looked at.
+                Some(Transformer::Project) => {
+                    return self.validate_project_file(context, init_path)
+                }
+                Some(Transformer::LuauModule) => Some("ModuleScript".to_owned()),
+                Some(Transformer::LuauServer) => Some("Script".to_owned()),
+                Some(Transformer::LuauClient) => Some("LocalScript".to_owned()),
+                Some(Transformer::Csv) => Some("LocalizationTable".to_owned()),
+                Some(Transformer::Other(name)) => {
+                    self.push(
+                        Severity::Error,
+                        init_path,
+                        None,
+                        format!("Unknown rojo type: {}", name),
+                    );
+                    None
+                }
+                Some(_) | None => class_name,
+            };
+        }
+
+        let mut child_paths = Vec::new();
+
+        match self.vfs.read_dir(path) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => child_paths.push(entry.path().to_path_buf()),
+                        Err(err) => self.report_error(path, None, &err.into()),
+                    }
+                }
+            }
+            Err(err) => self.report_error(path, None, &err.into()),
+        }
+
+        child_paths.sort();
+
+        for child_path in child_paths {
+            let passes_filter_rules = context
+                .path_ignore_rules
+                .iter()
+                .all(|rule| rule.passes(&child_path));
+
+            if !passes_filter_rules || Some(&child_path) == init_path.as_ref() {
+                continue;
+            }
+
+            match self.vfs.metadata(&child_path) {
+                Ok(meta) if meta.is_dir() => {
+                    self.validate_dir(context, &child_path);
+                }
+                Ok(_) => {
+                    self.validate_file(context, &child_path);
+                }
+                Err(err) => self.report_error(&child_path, None, &err.into()),
+            }
+        }
+
+        self.validate_dir_meta(path, class_name)
+    }
+
+    /// Checks the `init.meta.json` or `init.meta.toml` file in the given
+    /// directory if there is one, returning the directory's class name after
+    /// it's been applied.
+    fn validate_dir_meta(&mut self, path: &Path, class_name: Option<String>) -> Option<String> {
+        let mut found = None;
+
+        for meta_path in dir_meta_paths(path) {
+            match self.vfs.read(&meta_path).with_not_found() {
+                Ok(Some(contents)) => {
+                    found = Some((meta_path, contents));
+                    break;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    self.report_error(&meta_path, None, &err.into());
+                    return class_name;
+                }
+            }
+        }
+
+        let (meta_path, contents) = match found {
+            Some(found) => found,
+            None => return class_name,
+        };
+
+        let meta = match DirectoryMetadata::from_slice(&contents, meta_path.clone()) {
+            Ok(meta) => meta,
+            Err(err) => {
+                self.report_error(&meta_path, None, &err);
+                return class_name;
+            }
+        };
+
+        let class_name = match (meta.class_name, class_name) {
+            (Some(_), Some(class_name)) if class_name != "Folder" => {
+                self.push(
+                    Severity::Error,
+                    &meta_path,
+                    Some("/className".to_owned()),
+                    "className in init.meta.json can only be specified if the \
+                     affected directory would turn into a Folder instance."
+                        .to_owned(),
+                );
+
+                Some(class_name)
+            }
+            (Some(meta_class), _) => Some(meta_class),
+            (None, class_name) => class_name,
+        };
+
+        let mut property_names: Vec<_> = meta.properties.keys().collect();
+        property_names.sort();
+
+        if let Some(class_name) = &class_name {
+            for key in property_names {
+                let unresolved = meta.properties[key].clone();
+
+                if let Err(err) = unresolved.resolve(class_name, key) {
+                    let pointer = format!("/properties/{}", escape_pointer(key));
+                    self.report_error(&meta_path, Some(pointer), &err);
+                }
+            }
+        }
+
+        let mut attribute_names: Vec<_> = meta.attributes.keys().collect();
+        attribute_names.sort();
+
+        for key in attribute_names {
+            let unresolved = meta.attributes[key].clone();
+
+            if let Err(err) = unresolved.resolve_unambiguous() {
+                let pointer = format!("/attributes/{}", escape_pointer(key));
+                self.report_error(&meta_path, Some(pointer), &err);
+            }
+        }
+
+        class_name
+    }
+
+    /// Checks a single file, returning the class name of the instance it turns
+    /// into if it could be figured out.
+    fn validate_file(&mut self, context: &InstanceContext, path: &Path) -> Option<String> {
+        // Nested projects are walked by us instead of the snapshot middleware
+        // so that every problem inside of them gets reported.
+        if let Some(Transformer::Project) = get_transformer(context, path) {
+            return self.validate_project_file(context, path);
+        }
+
+        match snapshot_from_vfs(context, self.vfs, path) {
+            Ok(Some(snapshot)) => Some(snapshot.class_name.into_owned()),
+            Ok(None) => None,
+            Err(err) => {
+                self.report_error(path, None, &err);
+                None
+            }
+        }
+    }
+
+    fn report_error(&mut self, path: &Path, pointer: Option<String>, err: &anyhow::Error) {
+        let (line, column) = match json_location(err) {
+            Some((line, column)) => (Some(line), Some(column)),
+            None => (None, None),
+        };
+
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            path: display_path(path),
+            pointer,
+            line,
+            column,
+            message: format!("{:#}", err),
+        });
+    }
+
+    fn push(&mut self, severity: Severity, path: &Path, pointer: Option<String>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: display_path(path),
+            pointer,
+            line: None,
+            column: None,
+            message,
+        });
+    }
+}
+
+/// Finds the line and column of a JSON syntax or data error anywhere in the
+/// given error's chain of causes.
+fn json_location(err: &anyhow::Error) -> Option<(usize, usize)> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<serde_json::Error>())
+        .filter(|json_err| json_err.line() > 0)
+        .map(|json_err| (json_err.line(), json_err.column()))
+}
+
+fn print_diagnostics(diagnostics: &[Diagnostic], color: ColorChoice) -> io::Result<()> {
+    let writer = BufferWriter::stdout(color);
+    let mut buffer = writer.buffer();
+
+    if diagnostics.is_empty() {
+        writeln!(&mut buffer, "No problems found.")?;
+        return writer.print(&buffer);
+    }
+
+    let mut red = ColorSpec::new();
+    red.set_fg(Some(Color::Red)).set_bold(true);
+
+    let mut yellow = ColorSpec::new();
+    yellow.set_fg(Some(Color::Yellow)).set_bold(true);
+
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Error => {
+                buffer.set_color(&red)?;
+                write!(&mut buffer, "error")?;
+            }
+            Severity::Warning => {
+                buffer.set_color(&yellow)?;
+                write!(&mut buffer, "warning")?;
+            }
+        }
+
+        buffer.set_color(&ColorSpec::new())?;
+        write!(&mut buffer, ": {}", diagnostic.path.display())?;
+
+        if let (Some(line), Some(column)) = (diagnostic.line, diagnostic.column) {
+            write!(&mut buffer, ":{}:{}", line, column)?;
+        }
+
+        if let Some(pointer) = &diagnostic.pointer {
+            write!(&mut buffer, " ({})", pointer)?;
+        }
+
+        writeln!(&mut buffer)?;
+        writeln!(&mut buffer, "    {}", diagnostic.message)?;
+    }
+
+    let error_count = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+
+    writeln!(
+        &mut buffer,
+        "\n{} error(s), {} warning(s)",
+        error_count,
+        diagnostics.len() - error_count
+    )?;
+
+    writer.print(&buffer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::iter;
+
+    use maplit::hashmap;
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    /// Validates the project file at `/foo/default.project.json` after
+    /// loading the given files into `/foo`.
+    fn validate(files: VfsSnapshot) -> Vec<Diagnostic> {
+        validate_with_options(files, &[])
+    }
+
+    /// Like `validate`, with options given as if on the command line.
+    fn validate_with_options(files: VfsSnapshot, args: &[&str]) -> Vec<Diagnostic> {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo", files).unwrap();
+
+        let options =
+            ProjectOptions::parse_from(iter::once("validate").chain(args.iter().copied()));
+
+        let vfs = Vfs::new(imfs);
+        let mut validator = Validator::new(&vfs);
+        validator.validate_project_file(
+            &options.instance_context(),
+            Path::new("/foo/default.project.json"),
+        );
+
+        validator.diagnostics
+    }
+
+    fn locations(diagnostics: &[Diagnostic]) -> Vec<(Severity, Option<&str>)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.pointer.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_project() {
+        let diagnostics = validate(VfsSnapshot::dir(hashmap! {
+            "default.project.json" => VfsSnapshot::file(r#"
+                {
+                    "name": "valid",
+                    "tree": {
+                        "$path": "src"
+                    }
+                }
+            "#),
+            "src" => VfsSnapshot::dir(hashmap! {
+                "main.lua" => VfsSnapshot::file("print('Hello, world!')"),
+            }),
+        }));
+
+        assert!(locations(&diagnostics).is_empty());
+    }
+
+    #[test]
+    fn reports_every_problem_in_project() {
+        let diagnostics = validate(VfsSnapshot::dir(hashmap! {
+            "default.project.json" => VfsSnapshot::file(r#"
+                {
+                    "name": "problems",
+                    "tree": {
+                        "$className": "Folder",
+                        "Missing": {
+                            "$path": "missing.lua"
+                        },
+                        "Strange": {
+                            "$className": "NotARealClass"
+                        },
+                        "Value": {
+                            "$className": "StringValue",
+                            "$properties": {
+                                "Name": "Renamed",
+                                "Value": 5
+                            }
+                        }
+                    }
+                }
+            "#),
+        }));
+
+        assert_eq!(
+            locations(&diagnostics),
+            [
+                (Severity::Error, Some("/tree/Missing/$path")),
+                (Severity::Warning, Some("/tree/Strange/$className")),
+                (Severity::Warning, Some("/tree/Value/$properties/Name")),
+                (Severity::Error, Some("/tree/Value/$properties/Value")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_class_name_in_meta_of_script_folder() {
+        let diagnostics = validate(VfsSnapshot::dir(hashmap! {
+            "default.project.json" => VfsSnapshot::file(r#"
+                {
+                    "name": "meta",
+                    "tree": {
+                        "$path": "src"
+                    }
+                }
+            "#),
+            "src" => VfsSnapshot::dir(hashmap! {
+                "init.lua" => VfsSnapshot::file("return {}"),
+                "init.meta.json" => VfsSnapshot::file(r#"{ "className": "Configuration" }"#),
+            }),
+        }));
+
+        assert_eq!(
+            locations(&diagnostics),
+            [(Severity::Error, Some("/className"))]
+        );
+        assert_eq!(diagnostics[0].path, Path::new("/foo/src/init.meta.json"));
+    }
+
+    #[test]
+    fn reports_location_of_json_errors() {
+        let diagnostics = validate(VfsSnapshot::dir(hashmap! {
+            "default.project.json" => VfsSnapshot::file("{\n  \"name\": \"broken\",\n  \"tree\":\n}"),
+        }));
+
+        assert_eq!(locations(&diagnostics), [(Severity::Error, None)]);
+        assert_eq!(diagnostics[0].line, Some(4));
+    }
+
+    #[test]
+    fn uses_variables_and_profile() {
+        let files = || {
+            VfsSnapshot::dir(hashmap! {
+                "default.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "options",
+                        "variables": {},
+                        "tree": {
+                            "$className": "Folder",
+                            "Source": {
+                                "$path": "${ROJO_TEST_SOURCE}"
+                            },
+                            "Debug": {
+                                "$path": "debug"
+                            }
+                        },
+                        "profiles": {
+                            "production": {
+                                "exclude": ["Debug"]
+                            }
+                        }
+                    }
+                "#),
+                "src" => VfsSnapshot::dir(hashmap! {
+                    "main.lua" => VfsSnapshot::file("print('Hello, world!')"),
+                }),
+            })
+        };
+
+        let diagnostics = validate_with_options(
+            files(),
+            &["--var", "ROJO_TEST_SOURCE=src", "--profile", "production"],
+        );
+        assert!(locations(&diagnostics).is_empty());
+
+        // Without the options, the variable is missing, so the project fails
+        // to load.
+        let diagnostics = validate(files());
+        assert_eq!(locations(&diagnostics), [(Severity::Error, None)]);
+
+        // Without the profile, Debug's missing folder is a problem too.
+        let diagnostics = validate_with_options(
+            files(),
+            &["--var", "ROJO_TEST_SOURCE=src", "--profile", "staging"],
+        );
+        assert_eq!(
+            locations(&diagnostics),
+            [
+                (Severity::Error, None),
+                (Severity::Error, Some("/tree/Debug/$path")),
+            ]
+        );
+        assert!(diagnostics[0].message.contains("staging"));
+    }
+}
@@ -4,6 +4,7 @@
 use std::path::Path;
 
 use globset::{Glob as InnerGlob, GlobMatcher};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
 pub use globset::Error;
@@ -25,6 +26,17 @@ impl Glob {
     pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
         self.matcher.is_match(path)
     }
+
+    /// Whether the glob is an absolute path, which is matched against whole
+    /// paths instead of paths relative to the project that defines it. Globs
+    /// inherited from a base project in another folder are absolute.
+    pub fn is_absolute(&self) -> bool {
+        Path::new(self.as_str()).is_absolute()
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.inner.glob()
+    }
 }
 
 impl PartialEq for Glob {
@@ -48,3 +60,13 @@ impl<'de> Deserialize<'de> for Glob {
         Glob::new(glob).map_err(D::Error::custom)
     }
 }
+
+impl JsonSchema for Glob {
+    fn schema_name() -> String {
+        "Glob".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
@@ -0,0 +1,155 @@
+//! Runs the commands that projects can ask Rojo to run while building them.
+
+use std::{
+    ffi::OsStr,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context};
+use memofs::{IoResultExt, Vfs};
+
+use crate::project::PreBuildHook;
+
+/// Runs a hook's command with the system shell from `working_dir`, with the
+/// given extra environment variables set.
+///
+/// The command's output is forwarded to stderr so that it doesn't get mixed
+/// into anything Rojo writes to stdout, like a sourcemap.
+pub fn run_hook(command: &str, working_dir: &Path, envs: &[(&str, &OsStr)]) -> anyhow::Result<()> {
+    log::info!("Running hook: {}", command);
+
+    let output = shell_command(command)
+        .current_dir(working_dir)
+        .envs(envs.iter().copied())
+        .output()
+        .with_context(|| format!("Could not start hook: {}", command))?;
+
+    let mut stderr = io::stderr();
+    stderr.write_all(&output.stdout)?;
+    stderr.write_all(&output.stderr)?;
+
+    if !output.status.success() {
+        bail!("Hook `{}` failed ({})", command, output.status);
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+/// The pre-build hooks of a project, along with the folder they run from.
+#[derive(Debug, Clone)]
+pub struct PreBuildHooks {
+    hooks: Vec<PreBuildHook>,
+    root_dir: PathBuf,
+}
+
+impl PreBuildHooks {
+    pub fn new(hooks: Vec<PreBuildHook>, root_dir: PathBuf) -> Self {
+        Self { hooks, root_dir }
+    }
+
+    /// Runs every hook in order, stopping at the first one that fails.
+    pub fn run_all(&self) -> anyhow::Result<()> {
+        for hook in &self.hooks {
+            run_hook(&hook.command, &self.root_dir, &[])
+                .context("A pre-build hook failed, so the project was not built")?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs every hook that has an input matching the given path, which has
+    /// just changed.
+    pub fn run_affected(&self, path: &Path) -> anyhow::Result<()> {
+        let relative_path = match path.strip_prefix(&self.root_dir) {
+            Ok(relative_path) => relative_path,
+            Err(_) => return Ok(()),
+        };
+
+        for hook in &self.hooks {
+            if hook.inputs.iter().any(|glob| glob.is_match(relative_path)) {
+                run_hook(&hook.command, &self.root_dir, &[]).with_context(|| {
+                    format!(
+                        "A pre-build hook failed after {} changed. \
+                         The project may be out of date until it succeeds.",
+                        path.display()
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Asks the VFS to watch every file and folder that could match one of
+    /// the hooks' inputs, so that changing them raises events.
+    pub fn watch_inputs(&self, vfs: &Vfs) -> io::Result<()> {
+        for glob in self.hooks.iter().flat_map(|hook| &hook.inputs) {
+            let base = self.root_dir.join(glob_base(glob.as_str()));
+
+            match vfs.metadata(&base).with_not_found()? {
+                Some(meta) if meta.is_file() => {
+                    vfs.read(&base)?;
+                }
+                Some(_) => watch_dir_all(vfs, &base)?,
+                None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a folder and all of the folders inside it, which makes the VFS watch
+/// each of them.
+fn watch_dir_all(vfs: &Vfs, path: &Path) -> io::Result<()> {
+    let mut to_visit = vec![path.to_path_buf()];
+
+    while let Some(dir) = to_visit.pop() {
+        for entry in vfs.read_dir(&dir)? {
+            let entry_path = entry?.path().to_path_buf();
+
+            if vfs.metadata(&entry_path)?.is_dir() {
+                to_visit.push(entry_path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the part of a glob before any of its wildcards, which is the
+/// deepest path that contains everything the glob can match.
+fn glob_base(glob: &str) -> PathBuf {
+    glob.split('/')
+        .take_while(|component| !component.contains(['*', '?', '[', '{']))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glob_base_stops_at_wildcards() {
+        assert_eq!(glob_base("schemas/**/*.proto"), PathBuf::from("schemas"));
+        assert_eq!(glob_base("a/b/c?.txt"), PathBuf::from("a/b"));
+        assert_eq!(glob_base("schema.proto"), PathBuf::from("schema.proto"));
+        assert_eq!(glob_base("**/*.proto"), PathBuf::new());
+    }
+}
@@ -0,0 +1,126 @@
+//! Reads JSON that contains comments and trailing commas, which Rojo accepts
+//! in the JSON files it defines, like project and meta files.
+
+use std::borrow::Cow;
+
+use serde::de::DeserializeOwned;
+
+/// Deserializes JSON that may contain comments and trailing commas.
+pub fn from_slice<T: DeserializeOwned>(contents: &[u8]) -> serde_json::Result<T> {
+    serde_json::from_slice(&strip(contents))
+}
+
+/// Turns JSON with comments and trailing commas into plain JSON.
+///
+/// Comments and trailing commas are replaced with spaces instead of being
+/// removed, so that errors in the result point at the right line and column
+/// of the original.
+pub fn strip(contents: &[u8]) -> Cow<'_, [u8]> {
+    let mut output = Cow::Borrowed(contents);
+    let mut pending_comma = None;
+    let mut i = 0;
+
+    while i < contents.len() {
+        match (contents[i], contents.get(i + 1)) {
+            (b'"', _) => {
+                pending_comma = None;
+                i += 1;
+
+                while i < contents.len() {
+                    match contents[i] {
+                        b'\\' => i += 2,
+                        b'"' => {
+                            i += 1;
+                            break;
+                        }
+                        _ => i += 1,
+                    }
+                }
+
+                continue;
+            }
+            (b'/', Some(b'/')) => {
+                let end = contents[i..]
+                    .iter()
+                    .position(|&byte| byte == b'\n')
+                    .map_or(contents.len(), |offset| i + offset);
+
+                blank(output.to_mut(), i..end);
+                i = end;
+                continue;
+            }
+            (b'/', Some(b'*')) => {
+                let end = contents[i + 2..]
+                    .windows(2)
+                    .position(|window| window == b"*/")
+                    .map_or(contents.len(), |offset| i + 2 + offset + 2);
+
+                blank(output.to_mut(), i..end);
+                i = end;
+                continue;
+            }
+            (b',', _) => pending_comma = Some(i),
+            (b'}' | b']', _) => {
+                if let Some(comma) = pending_comma.take() {
+                    output.to_mut()[comma] = b' ';
+                }
+            }
+            (byte, _) if byte.is_ascii_whitespace() => {}
+            _ => pending_comma = None,
+        }
+
+        i += 1;
+    }
+
+    output
+}
+
+/// Replaces everything in the range with spaces, keeping line breaks.
+fn blank(output: &mut [u8], range: std::ops::Range<usize>) {
+    for byte in &mut output[range] {
+        if *byte != b'\n' && *byte != b'\r' {
+            *byte = b' ';
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strips_comments_and_trailing_commas() {
+        let contents = br#"{
+            // The name of the project
+            "name": "foo", /* inline */
+            "url": "https://example.com/*not a comment*/",
+            "globs": ["a", "b",],
+        }"#;
+
+        let value: serde_json::Value = from_slice(contents).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "name": "foo",
+                "url": "https://example.com/*not a comment*/",
+                "globs": ["a", "b"],
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_plain_json_as_is() {
+        let contents = br#"{ "path": "src//lib", "list": [1, 2] }"#;
+
+        assert!(matches!(strip(contents), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn keeps_error_lines() {
+        let contents = b"{\n  // comment\n  \"a\": 1,\n  \"b\": nope\n}";
+        let error = from_slice::<serde_json::Value>(contents).unwrap_err();
+
+        assert_eq!(error.line(), 4);
+    }
+}
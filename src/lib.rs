@@ -10,7 +10,10 @@ mod tree_view;
 mod auth_cookie;
 mod change_processor;
 mod glob;
+mod hooks;
+mod jsonc;
 mod lua_ast;
+mod manifest;
 mod message_queue;
 mod multimap;
 mod path_serializer;
@@ -20,6 +23,8 @@ mod serve_session;
 mod session_id;
 mod snapshot;
 mod snapshot_middleware;
+mod stamp;
+mod variables;
 mod web;
 
 pub use project::*;
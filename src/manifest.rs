@@ -0,0 +1,179 @@
+//! Defines the manifest that `rojo build --manifest` writes, which records
+//! where every instance in a build came from.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::{types::Ref, WeakDom};
+use serde::Serialize;
+
+use crate::snapshot::{InstanceMetadata, InstigatingSource};
+
+/// A record of every instance in a build and the files it came from.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildManifest {
+    /// The version of Rojo that did the build.
+    pub rojo_version: String,
+
+    /// Every file produced by the build.
+    pub outputs: Vec<ManifestOutput>,
+
+    /// A blake3 hash of every file that went into the build, keyed by its
+    /// path relative to the project folder.
+    pub files: BTreeMap<String, String>,
+
+    #[serde(skip)]
+    root_dir: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestOutput {
+    pub path: String,
+    pub kind: String,
+    pub instances: Vec<ManifestInstance>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestInstance {
+    /// The dotted path of the instance in the output, like
+    /// `ReplicatedStorage.Shared.Util`.
+    pub path: String,
+    pub class_name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instigating_source: Option<ManifestSource>,
+
+    /// The files that the instance was built from, which are also listed in
+    /// `BuildManifest::files`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub relevant_files: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ManifestSource {
+    /// The instance was created from a file.
+    #[serde(rename_all = "camelCase")]
+    File { path: String },
+
+    /// The instance was described by a node in a project file.
+    #[serde(rename_all = "camelCase")]
+    ProjectNode { project_file: String, name: String },
+}
+
+impl BuildManifest {
+    /// Creates an empty manifest. Paths in the manifest are written relative
+    /// to `root_dir`, the folder containing the project file.
+    pub fn new(root_dir: &Path) -> Self {
+        Self {
+            rojo_version: env!("CARGO_PKG_VERSION").to_owned(),
+            outputs: Vec::new(),
+            files: BTreeMap::new(),
+            root_dir: root_dir.to_path_buf(),
+        }
+    }
+
+    /// Records an output made of the instance `root_id` in `dom` and its
+    /// descendants, which should be exactly what was written to the output.
+    ///
+    /// `metadata_of` gives the metadata of the project instance that each
+    /// instance in the dom was built from. Instances without any, like the
+    /// ones that came from a base place, are listed without any sources.
+    pub fn add_output<'a>(
+        &mut self,
+        dom: &WeakDom,
+        root_id: Ref,
+        metadata_of: impl Fn(Ref) -> Option<&'a InstanceMetadata>,
+        vfs: &Vfs,
+        output_path: &Path,
+        kind: &str,
+    ) -> anyhow::Result<()> {
+        let mut instances = Vec::new();
+
+        let root = dom.get_by_ref(root_id).unwrap();
+        let mut to_visit = vec![(root_id, root.name.clone())];
+
+        while let Some((id, full_name)) = to_visit.pop() {
+            let instance = dom.get_by_ref(id).unwrap();
+            let metadata = metadata_of(id);
+
+            let instigating_source = metadata
+                .and_then(|metadata| metadata.instigating_source.as_ref())
+                .map(|source| match source {
+                    InstigatingSource::Path(path) => ManifestSource::File {
+                        path: self.display_path(path),
+                    },
+                    InstigatingSource::ProjectNode(path, name, _, _) => {
+                        ManifestSource::ProjectNode {
+                            project_file: self.display_path(path),
+                            name: name.clone(),
+                        }
+                    }
+                });
+
+            let relevant_paths =
+                metadata.map_or(&[][..], |metadata| metadata.relevant_paths.as_slice());
+            let mut relevant_files = Vec::new();
+
+            for path in relevant_paths {
+                match vfs.metadata(path).with_not_found()? {
+                    Some(meta) if meta.is_file() => {}
+                    _ => continue,
+                }
+
+                let display_path = self.display_path(path);
+
+                if !self.files.contains_key(&display_path) {
+                    let contents = vfs.read(path)?;
+                    let hash = blake3::hash(&contents).to_hex().to_string();
+                    self.files.insert(display_path.clone(), hash);
+                }
+
+                relevant_files.push(display_path);
+            }
+
+            // Like `RojoTree::get_full_name`, a DataModel's name is left out of
+            // the paths of its descendants.
+            let child_prefix = if id == root_id && instance.class == "DataModel" {
+                String::new()
+            } else {
+                format!("{}.", full_name)
+            };
+
+            // Children are pushed in reverse so that they're listed in order.
+            for &child_id in instance.children().iter().rev() {
+                let child = dom.get_by_ref(child_id).unwrap();
+                to_visit.push((child_id, format!("{}{}", child_prefix, child.name)));
+            }
+
+            instances.push(ManifestInstance {
+                path: full_name,
+                class_name: instance.class.clone(),
+                instigating_source,
+                relevant_files,
+            });
+        }
+
+        self.outputs.push(ManifestOutput {
+            path: self.display_path(output_path),
+            kind: kind.to_owned(),
+            instances,
+        });
+
+        Ok(())
+    }
+
+    /// Formats a path relative to the project folder with forward slashes, so
+    /// that manifests don't depend on where or on which platform the project
+    /// was built.
+    fn display_path(&self, path: &Path) -> String {
+        let path = path.strip_prefix(&self.root_dir).unwrap_or(path);
+        path.to_string_lossy().replace('\\', "/")
+    }
+}
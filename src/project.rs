@@ -1,16 +1,26 @@
 use std::{
+    borrow::Cow,
     collections::{BTreeMap, HashMap, HashSet},
-    fs, io,
+    env, fs, io,
     net::IpAddr,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use thiserror::Error;
 
-use crate::{glob::Glob, resolution::UnresolvedValue};
+use crate::{
+    glob::Glob,
+    jsonc,
+    resolution::UnresolvedValue,
+    variables::{interpolate, VariableError},
+};
 
-static PROJECT_FILENAME: &str = "default.project.json";
+/// The names of the project files that Rojo looks for in a folder, in order of
+/// preference.
+static PROJECT_FILENAMES: [&str; 2] = ["default.project.json", "default.project.toml"];
 
 /// Error type returned by any function that handles projects.
 #[derive(Debug, Error)]
@@ -30,14 +40,54 @@ enum Error {
         source: serde_json::Error,
         path: PathBuf,
     },
+
+    #[error("Error parsing Rojo project in path {}", .path.display())]
+    Toml {
+        source: toml::de::Error,
+        path: PathBuf,
+    },
+
+    #[error("Could not read project {}, which is extended by {}", .path.display(), .extended_by.display())]
+    Extends {
+        source: io::Error,
+        path: PathBuf,
+        extended_by: PathBuf,
+    },
+
+    #[error("Project {} extends itself through {}", .path.display(), .extended_by.display())]
+    ExtendsCycle { path: PathBuf, extended_by: PathBuf },
+
+    #[error("Could not substitute variables into Rojo project in path {}", .path.display())]
+    Variable {
+        source: VariableError,
+        path: PathBuf,
+    },
 }
 
 /// Contains all of the configuration for a Rojo-managed project.
 ///
-/// Project files are stored in `.project.json` files.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A project can extend another project file with `extends`. The base project
+/// is deep-merged underneath it: objects like `tree` are merged key by key,
+/// `globIgnorePaths` and `transformerRules` are appended to, and any other
+/// value in the extending project, including property values, replaces the
+/// base's. Setting a key to `null` removes it from the base.
+///
+/// Strings in a project that declares `variables`, even an empty set, can refer
+/// to variables like `${PLACE_ID}` or `${API_URL:-https://localhost}`. A
+/// variable is looked up in the values given with `--var`, then the project's
+/// `variables`, then the environment. Projects without `variables` are used
+/// as written, so older projects containing a literal `${` still load.
+///
+/// Project files are stored in `.project.json` files, which can contain
+/// comments and trailing commas, or in `.project.toml` files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct Project {
+    /// The path of a project file that this project extends, relative to the
+    /// folder this project file is in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<PathBuf>,
+
     /// The name of the top-level instance described by the project.
     pub name: String,
 
@@ -45,9 +95,17 @@ pub struct Project {
     /// describe at least one instance.
     pub tree: ProjectNode,
 
+    /// Values for variables used elsewhere in the project. These can refer to
+    /// environment variables and variables given with `--var`, but not to
+    /// each other. Variables are only substituted into projects that have
+    /// this, even if it's empty.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+
     /// If specified, sets the default port that `rojo serve` should use when
     /// using this project for live sync.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "port_schema")]
     pub serve_port: Option<u16>,
 
     /// If specified, contains the set of place IDs that this project is
@@ -55,17 +113,20 @@ pub struct Project {
     ///
     /// This setting is intended to help prevent syncing a Rojo project into the
     /// wrong Roblox place.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "id_set_schema")]
     pub serve_place_ids: Option<HashSet<u64>>,
 
     /// If specified, sets the current place's place ID when connecting to the
     /// Rojo server from Roblox Studio.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "id_schema")]
     pub place_id: Option<u64>,
 
     /// If specified, sets the current place's game ID when connecting to the
     /// Rojo server from Roblox Studio.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "id_schema")]
     pub game_id: Option<u64>,
 
     /// If specified, this address will be used in place of the default address
@@ -82,11 +143,36 @@ pub struct Project {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub transformer_rules: Vec<ProjectTransformerRule>,
 
+    /// Named outputs that `rojo build` can produce from this project, like a
+    /// place file and a model containing only shared code.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub build_targets: BTreeMap<String, BuildTarget>,
+
+    /// If specified, `rojo build` stamps information about the build onto an
+    /// instance as attributes, like the build time and git commit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_stamp: Option<BuildStamp>,
+
+    /// Commands that Rojo runs at certain points while building or serving
+    /// the project.
+    #[serde(default, skip_serializing_if = "ProjectHooks::is_empty")]
+    pub hooks: ProjectHooks,
+
+    /// Named variations of the project, like `production`, that can be
+    /// selected with `--profile`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProjectProfile>,
+
     /// The path to the file that this project came from. Relative paths in the
     /// project should be considered relative to the parent of this field, also
     /// given by `Project::folder_location`.
     #[serde(skip)]
     pub file_location: PathBuf,
+
+    /// Every project file that this project extends, directly or through
+    /// another base project. Changing any of them changes this project.
+    #[serde(skip)]
+    pub extended_files: Vec<PathBuf>,
 }
 
 impl Project {
@@ -94,14 +180,25 @@ impl Project {
     pub fn is_project_file(path: &Path) -> bool {
         path.file_name()
             .and_then(|name| name.to_str())
-            .map(|name| name.ends_with(".project.json"))
+            .map(|name| name.ends_with(".project.json") || name.ends_with(".project.toml"))
             .unwrap_or(false)
     }
 
+    /// Returns the path of the default project file in the given folder. If
+    /// there isn't one, this is where `default.project.json` would be.
+    pub fn default_path(folder: &Path) -> PathBuf {
+        PROJECT_FILENAMES
+            .iter()
+            .map(|name| folder.join(name))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| folder.join(PROJECT_FILENAMES[0]))
+    }
+
     /// Attempt to locate a project represented by the given path.
     ///
-    /// This will find a project if the path refers to a `.project.json` file,
-    /// or is a folder that contains a `default.project.json` file.
+    /// This will find a project if the path refers to a `.project.json` or
+    /// `.project.toml` file, or is a folder that contains a
+    /// `default.project.json` or `default.project.toml` file.
     fn locate(path: &Path) -> Option<PathBuf> {
         let meta = fs::metadata(path).ok()?;
 
@@ -112,18 +209,14 @@ impl Project {
                 None
             }
         } else {
-            let child_path = path.join(PROJECT_FILENAME);
-            let child_meta = fs::metadata(&child_path).ok()?;
-
-            if child_meta.is_file() {
-                Some(child_path)
-            } else {
-                // This is a folder with the same name as a Rojo default project
-                // file.
-                //
-                // That's pretty weird, but we can roll with it.
-                None
-            }
+            // A folder with the same name as a Rojo default project file is
+            // pretty weird, but we can roll with it.
+            PROJECT_FILENAMES
+                .iter()
+                .map(|name| path.join(name))
+                .find(|child_path| {
+                    fs::metadata(child_path).map_or(false, |child_meta| child_meta.is_file())
+                })
         }
     }
 
@@ -131,15 +224,17 @@ impl Project {
         contents: &[u8],
         project_file_location: &Path,
     ) -> Result<Self, ProjectError> {
-        let mut project: Self =
-            serde_json::from_slice(&contents).map_err(|source| Error::Json {
-                source,
-                path: project_file_location.to_owned(),
-            })?;
+        Self::load_from_slice_with_variables(contents, project_file_location, &BTreeMap::new())
+    }
 
-        project.file_location = project_file_location.to_path_buf();
-        project.check_compatibility();
-        Ok(project)
+    /// Loads a project like `load_from_slice`, with `variables` overriding any
+    /// of the same name from the project or the environment.
+    pub fn load_from_slice_with_variables(
+        contents: &[u8],
+        project_file_location: &Path,
+        variables: &BTreeMap<String, String>,
+    ) -> Result<Self, ProjectError> {
+        Ok(Self::parse(contents, project_file_location, variables)?)
     }
 
     pub fn load_fuzzy(fuzzy_project_location: &Path) -> Result<Option<Self>, ProjectError> {
@@ -153,20 +248,104 @@ impl Project {
     }
 
     fn load_exact(project_file_location: &Path) -> Result<Self, Error> {
-        let contents = fs::read_to_string(project_file_location)?;
+        let contents = fs::read(project_file_location)?;
 
-        let mut project: Project =
-            serde_json::from_str(&contents).map_err(|source| Error::Json {
+        Self::parse(&contents, project_file_location, &BTreeMap::new())
+    }
+
+    fn parse(
+        contents: &[u8],
+        project_file_location: &Path,
+        variables: &BTreeMap<String, String>,
+    ) -> Result<Self, Error> {
+        let json_error = |source| Error::Json {
+            source,
+            path: project_file_location.to_owned(),
+        };
+
+        let mut value = parse_value(contents, project_file_location)?;
+        let mut extended_files = Vec::new();
+
+        if value.get("extends").map_or(false, Value::is_string) {
+            value = resolve_extends(value, project_file_location, &mut extended_files)?;
+        }
+
+        if !variables.is_empty() && value.get("variables").is_none() {
+            log::warn!(
+                "Variables were given with --var, but {} doesn't declare `variables`, so they \
+                 weren't substituted. Add `\"variables\": {{}}` to the project to use them.",
+                project_file_location.display()
+            );
+        }
+
+        let substituted =
+            substitute_variables(&mut value, variables).map_err(|source| Error::Variable {
                 source,
                 path: project_file_location.to_owned(),
             })?;
 
+        // JSON projects that are used as written are deserialized straight
+        // from their contents so that errors point at the right line.
+        let is_json = !is_toml(project_file_location);
+        let mut project: Project = if is_json && extended_files.is_empty() && !substituted {
+            jsonc::from_slice(contents).map_err(json_error)?
+        } else {
+            serde_json::from_value(value).map_err(json_error)?
+        };
+
+        project.extended_files = extended_files;
         project.file_location = project_file_location.to_path_buf();
         project.check_compatibility();
 
         Ok(project)
     }
 
+    /// Applies the profile with the given name to the project, returning
+    /// whether the project has a profile with that name.
+    pub fn apply_profile(&mut self, name: &str) -> bool {
+        let profile = match self.profiles.get(name) {
+            Some(profile) => profile.clone(),
+            None => return false,
+        };
+
+        for path in &profile.exclude {
+            let (parent_path, child_name) = match path.rsplit_once('.') {
+                Some((parent_path, child_name)) => (parent_path, child_name),
+                None => ("", path.as_str()),
+            };
+
+            let removed = self
+                .tree
+                .find_node_mut(parent_path)
+                .and_then(|parent| parent.children.remove(child_name));
+
+            if removed.is_none() {
+                log::warn!(
+                    "Profile '{}' excludes {}, but there's no node with that path in {}",
+                    name,
+                    path,
+                    self.file_location.display()
+                );
+            }
+        }
+
+        for (path, properties) in profile.properties {
+            match self.tree.find_node_mut(&path) {
+                Some(node) => node.properties.extend(properties),
+                None => log::warn!(
+                    "Profile '{}' sets properties on {}, but there's no node with that path in {}",
+                    name,
+                    path,
+                    self.file_location.display()
+                ),
+            }
+        }
+
+        self.glob_ignore_paths.extend(profile.glob_ignore_paths);
+
+        true
+    }
+
     /// Checks if there are any compatibility issues with this project file and
     /// warns the user if there are any.
     fn check_compatibility(&self) {
@@ -178,7 +357,363 @@ impl Project {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension == "toml")
+}
+
+/// Parses a project file as TOML or JSON, depending on its extension, without
+/// deserializing it into a `Project` yet.
+fn parse_value(contents: &[u8], path: &Path) -> Result<Value, Error> {
+    let json_error = |source| Error::Json {
+        source,
+        path: path.to_owned(),
+    };
+
+    if is_toml(path) {
+        let value: toml::Value = toml::from_slice(contents).map_err(|source| Error::Toml {
+            source,
+            path: path.to_owned(),
+        })?;
+
+        serde_json::to_value(value).map_err(json_error)
+    } else {
+        jsonc::from_slice(contents).map_err(json_error)
+    }
+}
+
+/// Merges the project that `value` extends, and any project that one extends,
+/// underneath `value`. Every base project file is added to `extended_files`.
+fn resolve_extends(
+    mut value: Value,
+    project_file_location: &Path,
+    extended_files: &mut Vec<PathBuf>,
+) -> Result<Value, Error> {
+    let extends = match value.get("extends") {
+        Some(Value::String(extends)) => PathBuf::from(extends),
+        _ => return Ok(value),
+    };
+
+    // Paths are normalized so that a file reached through `..` is still
+    // recognized as one that's already part of the chain.
+    let project_file_location = normalize_path(project_file_location);
+    let project_folder = project_file_location.parent().unwrap();
+    let base_location = normalize_path(&project_folder.join(extends));
+
+    if base_location == project_file_location || extended_files.contains(&base_location) {
+        return Err(Error::ExtendsCycle {
+            path: base_location,
+            extended_by: project_file_location.to_owned(),
+        });
+    }
+
+    extended_files.push(base_location.clone());
+
+    let contents = fs::read(&base_location).map_err(|source| Error::Extends {
+        source,
+        path: base_location.clone(),
+        extended_by: project_file_location.to_owned(),
+    })?;
+
+    let base = parse_value(&contents, &base_location)?;
+    let mut base = resolve_extends(base, &base_location, extended_files)?;
+
+    // Paths and globs in the base project are relative to the base project,
+    // so they're made absolute before they end up in another project.
+    let base_folder = base_location.parent().unwrap();
+    if base_folder != project_folder {
+        rebase_project_paths(&mut base, base_folder);
+    }
+
+    // Lists of rules add to the base's, while any other list replaces it.
+    for key in ["globIgnorePaths", "transformerRules"] {
+        if let (Some(Value::Array(base_list)), Some(Value::Array(list))) =
+            (base.get_mut(key), value.get_mut(key))
+        {
+            list.splice(0..0, base_list.drain(..));
+        }
+    }
+
+    merge_values(&mut base, value);
+
+    Ok(base)
+}
+
+/// Substitutes variables into every string in a project that declares
+/// `variables`, returning whether any were found.
+fn substitute_variables(
+    project: &mut Value,
+    overrides: &BTreeMap<String, String>,
+) -> Result<bool, VariableError> {
+    let object = match project {
+        Value::Object(object) if object.contains_key("variables") => object,
+        _ => return Ok(false),
+    };
+
+    let outside_lookup = |name: &str| overrides.get(name).cloned().or_else(|| env::var(name).ok());
+
+    let mut defined = BTreeMap::new();
+    let mut substituted = false;
+
+    if let Some(Value::Object(variables)) = object.get_mut("variables") {
+        for (name, value) in variables.iter_mut() {
+            substituted |= substitute_strings(value, &outside_lookup)?;
+
+            if let Value::String(value) = value {
+                defined.insert(name.clone(), value.clone());
+            }
+        }
+    }
+
+    let lookup = |name: &str| {
+        overrides
+            .get(name)
+            .or_else(|| defined.get(name))
+            .cloned()
+            .or_else(|| env::var(name).ok())
+    };
+
+    for (key, value) in object.iter_mut() {
+        if key == "variables" {
+            continue;
+        }
+
+        substituted |= substitute_strings(value, &lookup)?;
+    }
+
+    // IDs and ports are numbers, but variables can only be substituted into
+    // strings, so those are converted back into numbers.
+    for key in ["placeId", "gameId", "servePort"] {
+        if let Some(value) = object.get_mut(key) {
+            string_to_number(value);
+        }
+    }
+
+    if let Some(Value::Array(place_ids)) = object.get_mut("servePlaceIds") {
+        place_ids.iter_mut().for_each(string_to_number);
+    }
+
+    Ok(substituted)
+}
+
+fn substitute_strings(
+    value: &mut Value,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<bool, VariableError> {
+    let mut substituted = false;
+
+    match value {
+        Value::String(string) => {
+            if let Cow::Owned(new_string) = interpolate(string, lookup)? {
+                *string = new_string;
+                substituted = true;
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                substituted |= substitute_strings(value, lookup)?;
+            }
+        }
+        Value::Object(object) => {
+            for value in object.values_mut() {
+                substituted |= substitute_strings(value, lookup)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(substituted)
+}
+
+fn string_to_number(value: &mut Value) {
+    if let Value::String(string) = value {
+        if let Ok(number) = string.parse::<u64>() {
+            *value = Value::from(number);
+        }
+    }
+}
+
+/// Describes a number in a project that can also be given as a string, so that
+/// it can be set with a variable. Those strings are converted into numbers by
+/// `substitute_variables`.
+fn number_or_variable_schema(number: Value) -> Schema {
+    serde_json::from_value(json!({
+        "anyOf": [number, { "type": "string" }]
+    }))
+    .expect("number schemas are always valid")
+}
+
+fn id_schema(_: &mut SchemaGenerator) -> Schema {
+    number_or_variable_schema(json!({ "type": "integer", "minimum": 0 }))
+}
+
+fn port_schema(_: &mut SchemaGenerator) -> Schema {
+    number_or_variable_schema(json!({ "type": "integer", "minimum": 0, "maximum": 65535 }))
+}
+
+fn id_set_schema(gen: &mut SchemaGenerator) -> Schema {
+    let id = serde_json::to_value(id_schema(gen)).expect("schemas can always be serialized");
+
+    serde_json::from_value(json!({
+        "type": "array",
+        "items": id,
+        "uniqueItems": true
+    }))
+    .expect("id set schemas are always valid")
+}
+
+/// Resolves `.` and `..` in a path without touching the file system.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            Component::ParentDir if normalized.has_root() => {}
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Makes every relative path and glob in a base project relative to `folder`,
+/// the folder containing the base project, instead.
+fn rebase_project_paths(project: &mut Value, folder: &Path) {
+    if let Some(tree) = project.get_mut("tree") {
+        rebase_node_paths(tree, folder);
+    }
+
+    if let Some(Value::Array(globs)) = project.get_mut("globIgnorePaths") {
+        for glob in globs {
+            rebase_glob(glob, folder);
+        }
+    }
+
+    if let Some(Value::Array(rules)) = project.get_mut("transformerRules") {
+        for rule in rules {
+            if let Some(pattern) = rule.get_mut("pattern") {
+                rebase_glob(pattern, folder);
+            }
+        }
+    }
+
+    if let Some(Value::Object(profiles)) = project.get_mut("profiles") {
+        for profile in profiles.values_mut() {
+            if let Some(Value::Array(globs)) = profile.get_mut("globIgnorePaths") {
+                for glob in globs {
+                    rebase_glob(glob, folder);
+                }
+            }
+        }
+    }
+}
+
+/// Turns a relative glob into an absolute one that matches the same paths
+/// inside `folder`. Absolute globs are matched against whole paths instead of
+/// paths relative to the project that defines them.
+fn rebase_glob(glob: &mut Value, folder: &Path) {
+    let glob = match glob {
+        Value::String(glob) => glob,
+        _ => return,
+    };
+
+    if Path::new(glob.as_str()).is_absolute() {
+        return;
+    }
+
+    let mut rebased = String::new();
+
+    for c in folder.to_string_lossy().chars() {
+        match c {
+            '*' | '?' | '[' | ']' | '{' | '}' => {
+                rebased.push('[');
+                rebased.push(c);
+                rebased.push(']');
+            }
+            '\\' if cfg!(windows) => rebased.push('/'),
+            '\\' => rebased.push_str("\\\\"),
+            _ => rebased.push(c),
+        }
+    }
+
+    if !rebased.ends_with('/') {
+        rebased.push('/');
+    }
+
+    rebased.push_str(glob);
+    *glob = rebased;
+}
+
+/// Makes every relative `$path` in a project node and its descendants
+/// relative to `folder` instead.
+fn rebase_node_paths(node: &mut Value, folder: &Path) {
+    let object = match node {
+        Value::Object(object) => object,
+        _ => return,
+    };
+
+    for (key, child) in object.iter_mut() {
+        if key == "$path" {
+            let path = match child {
+                Value::String(path) => path,
+                Value::Object(optional) => match optional.get_mut("optional") {
+                    Some(Value::String(path)) => path,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            if Path::new(path.as_str()).is_relative() {
+                *path = folder.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        } else if !key.starts_with('$') {
+            rebase_node_paths(child, folder);
+        }
+    }
+}
+
+/// Deep-merges `overlay` into `base`. Objects are merged key by key, and any
+/// other value, including an array, replaces what was in `base`. A `null` in
+/// an object removes that key from `base`.
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                    continue;
+                }
+
+                match (base.get_mut(&key), value) {
+                    // Property values are replaced as a whole, since merging
+                    // two values of different types would make an invalid one.
+                    (Some(Value::Object(existing)), Value::Object(values))
+                        if key == "$properties" || key == "$attributes" =>
+                    {
+                        for (name, value) in values {
+                            if value.is_null() {
+                                existing.remove(&name);
+                            } else {
+                                existing.insert(name, value);
+                            }
+                        }
+                    }
+                    (Some(existing), value) => merge_values(existing, value),
+                    (None, value) => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct ProjectTransformerRule {
     /// The glob pattern to match files against for this type override
@@ -189,7 +724,117 @@ pub struct ProjectTransformerRule {
     pub transformer_name: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+/// Describes one file that `rojo build --target` can produce.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct BuildTarget {
+    /// Where to write the built file, relative to the folder the project file
+    /// is in.
+    pub output: PathBuf,
+
+    /// The kind of file to build, like `rbxm` or `rbxlx`. If unset, the kind is
+    /// detected from the extension of `output`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    /// The dotted path of an instance, like `ReplicatedStorage.Shared`, to
+    /// build instead of the whole tree.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtree: Option<String>,
+
+    /// A list of globs, relative to the folder the project file is in. Any
+    /// instance that came from a matching file is left out of this target.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub glob_ignore_paths: Vec<Glob>,
+}
+
+/// A variation of a project, like a production build that leaves out test
+/// code. Nodes are given as dotted paths from the root of the project's tree,
+/// like `ServerScriptService.Tests`, where an empty path is the root itself.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct ProjectProfile {
+    /// Nodes to leave out of the project, along with everything in them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+
+    /// Properties to set on nodes, replacing any that the nodes already set.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, HashMap<String, UnresolvedValue>>,
+
+    /// More globs to ignore, added to the project's `globIgnorePaths`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub glob_ignore_paths: Vec<Glob>,
+}
+
+/// Describes how `rojo build` stamps information about the build into the
+/// built file.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct BuildStamp {
+    /// The dotted path of the instance to stamp, like
+    /// `ReplicatedStorage.Shared`. If unset, the root of the built file is
+    /// stamped, or ReplicatedStorage if the built file is a place.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+
+    /// A version string to stamp, like `1.4.0`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// Commands that Rojo runs at certain points while building or serving a
+/// project.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct ProjectHooks {
+    /// Commands to run before the project is first snapshotted, like code
+    /// generators whose output is part of the project. Only `rojo build` and
+    /// `rojo serve` run these.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_build: Vec<PreBuildHook>,
+
+    /// Commands to run after `rojo build` writes each output, like asset
+    /// checkers or tools that post-process the built file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_build: Vec<PostBuildHook>,
+}
+
+impl ProjectHooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_build.is_empty() && self.post_build.is_empty()
+    }
+}
+
+/// A command that runs before a project is built.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct PreBuildHook {
+    /// The command to run with the system shell, from the folder the project
+    /// file is in.
+    pub command: String,
+
+    /// A list of globs, relative to the folder the project file is in, that
+    /// match the files this command reads. When serving or building with
+    /// --watch, the command runs again whenever one of them changes.
+    ///
+    /// These should not match the files the command writes, or it will keep
+    /// running itself.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<Glob>,
+}
+
+/// A command that runs after a project is built.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct PostBuildHook {
+    /// The command to run with the system shell, from the folder the project
+    /// file is in. The built file's path is given in the `ROJO_OUTPUT_PATH`
+    /// environment variable, and its kind, like `rbxl`, in `ROJO_OUTPUT_KIND`.
+    pub command: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
 pub struct OptionalPathNode {
     #[serde(serialize_with = "crate::path_serializer::serialize_absolute")]
     pub optional: PathBuf,
@@ -202,7 +847,7 @@ impl OptionalPathNode {
 }
 
 /// Describes a path that is either optional or required
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum PathNode {
     Required(#[serde(serialize_with = "crate::path_serializer::serialize_absolute")] PathBuf),
@@ -219,7 +864,7 @@ impl PathNode {
 }
 
 /// Describes an instance and its descendants in a project.
-#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
 pub struct ProjectNode {
     /// If set, defines the ClassName of the described instance.
     ///
@@ -280,6 +925,17 @@ pub struct ProjectNode {
 }
 
 impl ProjectNode {
+    /// Finds a node from a dotted path of names relative to this node. An
+    /// empty path refers to this node.
+    fn find_node_mut(&mut self, path: &str) -> Option<&mut ProjectNode> {
+        if path.is_empty() {
+            return Some(self);
+        }
+
+        path.split('.')
+            .try_fold(self, |node, name| node.children.get_mut(name))
+    }
+
     fn validate_reserved_names(&self) {
         for (name, child) in &self.children {
             if name.starts_with('$') {
@@ -301,6 +957,10 @@ impl ProjectNode {
 mod test {
     use super::*;
 
+    use rbx_dom_weak::types::{Color3, Variant, Vector3};
+
+    use crate::snapshot::PathIgnoreRule;
+
     #[test]
     fn path_node_required() {
         let path_node: PathNode = serde_json::from_str(r#""src""#).unwrap();
@@ -398,4 +1058,405 @@ mod test {
         let serialized = serde_json::to_string(&project_node).unwrap();
         assert_eq!(serialized, r#"{"$path":"../src"}"#);
     }
+
+    #[test]
+    fn build_targets() {
+        let project: Project = serde_json::from_str(
+            r#"{
+                "name": "build-targets",
+                "tree": {
+                    "$className": "DataModel"
+                },
+                "buildTargets": {
+                    "place": {
+                        "output": "build/game.rbxl"
+                    },
+                    "shared": {
+                        "output": "build/shared.model",
+                        "kind": "rbxmx",
+                        "subtree": "ReplicatedStorage.Shared",
+                        "globIgnorePaths": ["**/*.spec.lua"]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let place = &project.build_targets["place"];
+        assert_eq!(place.output, PathBuf::from("build/game.rbxl"));
+        assert_eq!(place.kind, None);
+        assert_eq!(place.subtree, None);
+
+        let shared = &project.build_targets["shared"];
+        assert_eq!(shared.kind.as_deref(), Some("rbxmx"));
+        assert_eq!(shared.subtree.as_deref(), Some("ReplicatedStorage.Shared"));
+        assert_eq!(shared.glob_ignore_paths.len(), 1);
+    }
+
+    #[test]
+    fn pre_build_hooks() {
+        let project: Project = serde_json::from_str(
+            r#"{
+                "name": "hooks",
+                "tree": {
+                    "$className": "DataModel"
+                },
+                "hooks": {
+                    "preBuild": [
+                        {
+                            "command": "lune run codegen",
+                            "inputs": ["schemas/**/*.schema"]
+                        },
+                        {
+                            "command": "echo hello"
+                        }
+                    ],
+                    "postBuild": [
+                        {
+                            "command": "lune run check-assets"
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let hooks = &project.hooks.pre_build;
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(hooks[0].command, "lune run codegen");
+        assert!(hooks[0].inputs[0].is_match("schemas/game/items.schema"));
+        assert!(hooks[1].inputs.is_empty());
+
+        assert_eq!(project.hooks.post_build[0].command, "lune run check-assets");
+    }
+
+    #[test]
+    fn extends_merges_base_project() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("base")).unwrap();
+
+        fs::write(
+            dir.path().join("base/default.project.json"),
+            r#"{
+                "name": "base",
+                "globIgnorePaths": ["**/*.spec.lua"],
+                "servePort": 34872,
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": {
+                        "Shared": { "$path": "shared" }
+                    },
+                    "Workspace": { "$className": "Workspace" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let project_path = dir.path().join("game.project.json");
+        fs::write(
+            &project_path,
+            r#"{
+                "extends": "base/default.project.json",
+                "name": "game",
+                "globIgnorePaths": ["**/*.story.lua"],
+                "tree": {
+                    "ReplicatedStorage": {
+                        "Game": { "$path": "src" }
+                    },
+                    "Workspace": null
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let project = Project::load_exact(&project_path).unwrap();
+
+        assert_eq!(project.name, "game");
+        assert_eq!(project.serve_port, Some(34872));
+        assert_eq!(project.glob_ignore_paths.len(), 2);
+        assert_eq!(
+            project.extended_files,
+            vec![dir.path().join("base/default.project.json")]
+        );
+
+        let replicated_storage = &project.tree.children["ReplicatedStorage"];
+        assert_eq!(
+            replicated_storage.children["Shared"].path,
+            Some(PathNode::Required(dir.path().join("base/shared")))
+        );
+        assert_eq!(
+            replicated_storage.children["Game"].path,
+            Some(PathNode::Required(PathBuf::from("src")))
+        );
+        assert!(!project.tree.children.contains_key("Workspace"));
+
+        // The base's ignore glob still matches files next to the base project.
+        let ignore_rules: Vec<_> = project
+            .glob_ignore_paths
+            .iter()
+            .map(|glob| PathIgnoreRule {
+                glob: glob.clone(),
+                base_path: dir.path().to_path_buf(),
+            })
+            .collect();
+
+        assert!(!ignore_rules[0].passes(dir.path().join("base/shared/foo.spec.lua")));
+        assert!(ignore_rules[0].passes(dir.path().join("src/foo.spec.lua")));
+        assert!(!ignore_rules[1].passes(dir.path().join("src/foo.story.lua")));
+    }
+
+    #[test]
+    fn extends_replaces_property_values() {
+        let dir = tempfile::tempdir().unwrap();
+
+        fs::write(
+            dir.path().join("base.project.json"),
+            r#"{
+                "name": "base",
+                "tree": {
+                    "$className": "DataModel",
+                    "Workspace": {
+                        "$className": "Workspace",
+                        "Baseplate": {
+                            "$className": "Part",
+                            "$properties": {
+                                "Anchored": true,
+                                "Color": { "Color3uint8": [91, 93, 105] },
+                                "Size": [512, 20, 512]
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let project_path = dir.path().join("game.project.json");
+        fs::write(
+            &project_path,
+            r#"{
+                "extends": "base.project.json",
+                "name": "game",
+                "tree": {
+                    "Workspace": {
+                        "Baseplate": {
+                            "$properties": {
+                                "Color": { "Color3": [1, 0, 0] },
+                                "Size": [100, 1, 100]
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let project = Project::load_exact(&project_path).unwrap();
+        let properties = &project.tree.children["Workspace"].children["Baseplate"].properties;
+        let resolve = |name: &str| properties[name].clone().resolve("Part", name).unwrap();
+
+        assert_eq!(
+            resolve("Size"),
+            Variant::Vector3(Vector3::new(100.0, 1.0, 100.0))
+        );
+        assert_eq!(
+            resolve("Color"),
+            Variant::Color3(Color3::new(1.0, 0.0, 0.0))
+        );
+        assert_eq!(resolve("Anchored"), Variant::Bool(true));
+    }
+
+    #[test]
+    fn extends_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let a_path = dir.path().join("a.project.json");
+        let b_path = dir.path().join("b.project.json");
+
+        fs::write(&a_path, r#"{ "extends": "b.project.json", "name": "a" }"#).unwrap();
+        fs::write(&b_path, r#"{ "extends": "a.project.json", "name": "b" }"#).unwrap();
+
+        let error = Project::load_exact(&a_path).unwrap_err();
+        assert!(matches!(error, Error::ExtendsCycle { .. }));
+    }
+
+    #[test]
+    fn extends_cycle_through_parent_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("one")).unwrap();
+        fs::create_dir(dir.path().join("two")).unwrap();
+
+        let a_path = dir.path().join("one/a.project.json");
+        let b_path = dir.path().join("two/b.project.json");
+
+        fs::write(
+            &a_path,
+            r#"{ "extends": "../two/b.project.json", "name": "a" }"#,
+        )
+        .unwrap();
+        fs::write(
+            &b_path,
+            r#"{ "extends": "../one/./a.project.json", "name": "b" }"#,
+        )
+        .unwrap();
+
+        let error = Project::load_exact(&a_path).unwrap_err();
+        assert!(matches!(error, Error::ExtendsCycle { .. }));
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("ENVIRONMENT".to_owned(), "staging".to_owned());
+
+        let project = Project::load_from_slice_with_variables(
+            br#"{
+                "name": "game-${ENVIRONMENT}",
+                "variables": {
+                    "PLACE_ID": "1234",
+                    "API_URL": "https://${ENVIRONMENT}.example.com"
+                },
+                "placeId": "${PLACE_ID}",
+                "servePort": "${ROJO_TEST_UNSET_PORT:-34873}",
+                "tree": {
+                    "$className": "StringValue",
+                    "$properties": {
+                        "Value": "${API_URL}/v1"
+                    }
+                }
+            }"#,
+            Path::new("/foo/default.project.json"),
+            &overrides,
+        )
+        .unwrap();
+
+        assert_eq!(project.name, "game-staging");
+        assert_eq!(project.place_id, Some(1234));
+        assert_eq!(project.serve_port, Some(34873));
+        assert_eq!(
+            project.tree.properties["Value"]
+                .clone()
+                .resolve("StringValue", "Value")
+                .unwrap(),
+            Variant::String("https://staging.example.com/v1".to_owned())
+        );
+    }
+
+    #[test]
+    fn variables_need_to_be_declared() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("ENVIRONMENT".to_owned(), "staging".to_owned());
+
+        let project = Project::load_from_slice_with_variables(
+            br#"{
+                "name": "template",
+                "tree": {
+                    "$className": "StringValue",
+                    "$properties": {
+                        "Value": "Hello, ${NAME}! ${ENVIRONMENT}"
+                    }
+                }
+            }"#,
+            Path::new("/foo/default.project.json"),
+            &overrides,
+        )
+        .unwrap();
+
+        assert_eq!(
+            project.tree.properties["Value"]
+                .clone()
+                .resolve("StringValue", "Value")
+                .unwrap(),
+            Variant::String("Hello, ${NAME}! ${ENVIRONMENT}".to_owned())
+        );
+    }
+
+    #[test]
+    fn applies_profile() {
+        let mut project: Project = serde_json::from_str(
+            r#"{
+                "name": "profiles",
+                "tree": {
+                    "$className": "DataModel",
+                    "ReplicatedStorage": {
+                        "DebugTools": { "$path": "debug" },
+                        "Config": {
+                            "$className": "StringValue",
+                            "$properties": { "Value": "dev" }
+                        }
+                    }
+                },
+                "profiles": {
+                    "production": {
+                        "exclude": ["ReplicatedStorage.DebugTools"],
+                        "properties": {
+                            "ReplicatedStorage.Config": { "Value": "production" }
+                        },
+                        "globIgnorePaths": ["**/*.spec.lua"]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(!project.apply_profile("staging"));
+        assert!(project.apply_profile("production"));
+
+        let replicated_storage = &project.tree.children["ReplicatedStorage"];
+        assert!(!replicated_storage.children.contains_key("DebugTools"));
+        assert_eq!(
+            replicated_storage.children["Config"].properties["Value"]
+                .clone()
+                .resolve("StringValue", "Value")
+                .unwrap(),
+            Variant::String("production".to_owned())
+        );
+        assert_eq!(project.glob_ignore_paths.len(), 1);
+    }
+
+    #[test]
+    fn load_jsonc() {
+        let project = Project::load_from_slice(
+            br#"{
+                // Shared by every place in the game.
+                "name": "shared",
+                "tree": {
+                    "$path": "src", /* not "lib" */
+                },
+            }"#,
+            Path::new("/foo/default.project.json"),
+        )
+        .unwrap();
+
+        assert_eq!(project.name, "shared");
+        assert_eq!(
+            project.tree.path,
+            Some(PathNode::Required(PathBuf::from("src")))
+        );
+    }
+
+    #[test]
+    fn load_toml() {
+        let project = Project::load_from_slice(
+            br#"
+                name = "game"
+                servePort = 34873
+
+                [tree]
+                "$className" = "DataModel"
+
+                [tree.ReplicatedStorage.Shared]
+                "$path" = "src/shared"
+            "#,
+            Path::new("/foo/default.project.toml"),
+        )
+        .unwrap();
+
+        assert_eq!(project.name, "game");
+        assert_eq!(project.serve_port, Some(34873));
+        assert_eq!(
+            project.tree.children["ReplicatedStorage"].children["Shared"].path,
+            Some(PathNode::Required(PathBuf::from("src/shared")))
+        );
+    }
 }
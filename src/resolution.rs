@@ -6,6 +6,11 @@ use rbx_dom_weak::types::{
     Vector3,
 };
 use rbx_reflection::{DataType, PropertyDescriptor};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{Metadata, Schema, SchemaObject},
+    JsonSchema,
+};
 use serde::{Deserialize, Serialize};
 
 /// A user-friendly version of `Variant` that supports specifying ambiguous
@@ -21,6 +26,30 @@ pub enum UnresolvedValue {
     Ambiguous(AmbiguousValue),
 }
 
+impl JsonSchema for UnresolvedValue {
+    fn schema_name() -> String {
+        "PropertyValue".to_owned()
+    }
+
+    /// Property values can take too many shapes to describe usefully, since
+    /// ambiguous values depend on the type of the property they're set on.
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "The value of a property, either with its type given explicitly, like \
+                     { \"Vector3\": [1, 2, 3] }, or in a shorter form that depends on the \
+                     property's type, like [1, 2, 3]."
+                        .to_owned(),
+                ),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl UnresolvedValue {
     pub fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
         match self {
@@ -197,6 +226,44 @@ fn find_descriptor(
     }
 }
 
+/// Returns the default value that the reflection database lists for the given
+/// property, walking up the class hierarchy if needed.
+pub fn find_default_property(class_name: &str, prop_name: &str) -> Option<&'static Variant> {
+    let database = rbx_reflection_database::get();
+    let mut current_class_name = class_name;
+
+    loop {
+        let class = database.classes.get(current_class_name)?;
+        if let Some(value) = class.default_properties.get(prop_name) {
+            return Some(value);
+        }
+
+        current_class_name = class.superclass.as_deref()?;
+    }
+}
+
+/// Tells whether a property is worth writing out when turning an instance back
+/// into one of Rojo's file formats.
+///
+/// Values that match the reflection database's default are skipped, since
+/// leaving them out produces the same instance. Referents and per-instance
+/// identifiers are skipped because they're only meaningful inside the DOM they
+/// came from.
+pub fn should_persist_property(class_name: &str, prop_name: &str, value: &Variant) -> bool {
+    if let Variant::Ref(_) = value {
+        return false;
+    }
+
+    if matches!(prop_name, "UniqueId" | "ScriptGuid") {
+        return false;
+    }
+
+    match find_default_property(class_name, prop_name) {
+        Some(default) => default != value,
+        None => true,
+    }
+}
+
 /// Outputs a string containing up to MAX_ITEMS entries from the given list. If
 /// there are more than MAX_ITEMS items, the number of remaining items will be
 /// listed.
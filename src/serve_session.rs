@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     io,
     net::IpAddr,
     path::{Path, PathBuf},
@@ -15,8 +15,9 @@ use thiserror::Error;
 
 use crate::{
     change_processor::ChangeProcessor,
+    hooks::PreBuildHooks,
     message_queue::MessageQueue,
-    project::{Project, ProjectError},
+    project::{BuildStamp, BuildTarget, PostBuildHook, Project, ProjectError},
     session_id::SessionId,
     snapshot::{
         apply_patch_set, compute_patch_set, AppliedPatchSet, InstanceContext, InstanceSnapshot,
@@ -97,7 +98,43 @@ impl ServeSession {
     /// currently loaded from the filesystem directly instead of through the
     /// in-memory filesystem layer.
     pub fn new<P: AsRef<Path>>(vfs: Vfs, start_path: P) -> Result<Self, ServeSessionError> {
-        let start_path = start_path.as_ref();
+        Self::new_with_context(vfs, start_path, InstanceContext::default())
+    }
+
+    /// Start a new serve session like `new`, snapshotting the project with the
+    /// given context. This is how options from the command line, like
+    /// variables, reach every project file.
+    ///
+    /// The project's pre-build hooks are not run.
+    pub fn new_with_context<P: AsRef<Path>>(
+        vfs: Vfs,
+        start_path: P,
+        instance_context: InstanceContext,
+    ) -> Result<Self, ServeSessionError> {
+        Self::start(vfs, start_path.as_ref(), instance_context, false)
+    }
+
+    /// Start a new serve session like `new_with_context`, running the
+    /// project's pre-build hooks first and again whenever their inputs change.
+    ///
+    /// Hooks run arbitrary commands from the project file, so only commands
+    /// that build the project should use this. Commands that only read a
+    /// project, like `rojo sourcemap`, are often started by editors on their
+    /// own and must not run them.
+    pub fn new_with_hooks<P: AsRef<Path>>(
+        vfs: Vfs,
+        start_path: P,
+        instance_context: InstanceContext,
+    ) -> Result<Self, ServeSessionError> {
+        Self::start(vfs, start_path.as_ref(), instance_context, true)
+    }
+
+    fn start(
+        vfs: Vfs,
+        start_path: &Path,
+        instance_context: InstanceContext,
+        run_hooks: bool,
+    ) -> Result<Self, ServeSessionError> {
         let start_time = Instant::now();
 
         log::trace!("Starting new ServeSession at path {}", start_path.display());
@@ -106,13 +143,17 @@ impl ServeSession {
         if Project::is_project_file(start_path) {
             project_path = Cow::Borrowed(start_path);
         } else {
-            project_path = Cow::Owned(start_path.join("default.project.json"));
+            project_path = Cow::Owned(Project::default_path(start_path));
         }
 
         log::debug!("Loading project file from {}", project_path.display());
 
-        let root_project = match vfs.read(&project_path).with_not_found()? {
-            Some(contents) => Project::load_from_slice(&contents, &project_path)?,
+        let mut root_project = match vfs.read(&project_path).with_not_found()? {
+            Some(contents) => Project::load_from_slice_with_variables(
+                &contents,
+                &project_path,
+                &instance_context.variables,
+            )?,
             None => {
                 return Err(ServeSessionError::NoProjectFound {
                     path: project_path.to_path_buf(),
@@ -120,12 +161,37 @@ impl ServeSession {
             }
         };
 
+        if let Some(profile) = &instance_context.profile {
+            if !root_project.apply_profile(profile) {
+                return Err(ServeSessionError::UnknownProfile {
+                    name: profile.to_string(),
+                    path: project_path.to_path_buf(),
+                    available: root_project
+                        .profiles
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                });
+            }
+        }
+
+        let hooks = if run_hooks {
+            root_project.hooks.pre_build.clone()
+        } else {
+            Vec::new()
+        };
+        let pre_build_hooks =
+            PreBuildHooks::new(hooks, root_project.folder_location().to_path_buf());
+
+        log::trace!("Running pre-build hooks");
+        pre_build_hooks.run_all()?;
+        pre_build_hooks.watch_inputs(&vfs)?;
+
         let mut tree = RojoTree::new(InstanceSnapshot::new());
 
         let root_id = tree.get_root_id();
 
-        let instance_context = InstanceContext::default();
-
         log::trace!("Generating snapshot of instances from VFS");
         let snapshot = snapshot_from_vfs(&instance_context, &vfs, &start_path)?;
 
@@ -150,6 +216,7 @@ impl ServeSession {
             Arc::clone(&vfs),
             Arc::clone(&message_queue),
             tree_mutation_receiver,
+            pre_build_hooks,
         );
 
         Ok(Self {
@@ -217,9 +284,25 @@ impl ServeSession {
         self.root_project.serve_address
     }
 
+    pub fn project_path(&self) -> &Path {
+        &self.root_project.file_location
+    }
+
     pub fn root_dir(&self) -> &Path {
         self.root_project.folder_location()
     }
+
+    pub fn build_targets(&self) -> &BTreeMap<String, BuildTarget> {
+        &self.root_project.build_targets
+    }
+
+    pub fn build_stamp(&self) -> Option<&BuildStamp> {
+        self.root_project.build_stamp.as_ref()
+    }
+
+    pub fn post_build_hooks(&self) -> &[PostBuildHook] {
+        &self.root_project.hooks.post_build
+    }
 }
 
 #[derive(Debug, Error)]
@@ -231,6 +314,16 @@ pub enum ServeSessionError {
     )]
     NoProjectFound { path: PathBuf },
 
+    #[error(
+        "The project in path {} has no profile named '{name}'. Its profiles are: {available}",
+        .path.display()
+    )]
+    UnknownProfile {
+        name: String,
+        path: PathBuf,
+        available: String,
+    },
+
     #[error(transparent)]
     Io {
         #[from]
@@ -8,7 +8,7 @@ use rbx_dom_weak::{
 };
 use serde::{Deserialize, Serialize};
 
-use super::InstanceMetadata;
+use super::{InstanceMetadata, RojoTree};
 
 /// A lightweight description of what an instance should look like.
 ///
@@ -108,6 +108,32 @@ impl InstanceSnapshot {
         Self::from_raw_tree(&mut raw_tree, id)
     }
 
+    /// Creates a snapshot of an instance that's already part of a `RojoTree`,
+    /// along with its descendants and metadata.
+    ///
+    /// Instance IDs are reused as snapshot IDs so that Ref properties keep
+    /// pointing at the right instances when the snapshot is applied elsewhere.
+    pub fn from_rojo_tree(tree: &RojoTree, id: Ref) -> Self {
+        let instance = tree
+            .get_instance(id)
+            .expect("instance did not exist in tree");
+
+        let children = instance
+            .children()
+            .iter()
+            .map(|&id| Self::from_rojo_tree(tree, id))
+            .collect();
+
+        Self {
+            snapshot_id: Some(id),
+            metadata: instance.metadata().clone(),
+            name: Cow::Owned(instance.name().to_owned()),
+            class_name: Cow::Owned(instance.class_name().to_owned()),
+            properties: instance.properties().clone(),
+            children,
+        }
+    }
+
     fn from_raw_tree(raw_tree: &mut HashMap<Ref, Instance>, id: Ref) -> Self {
         let instance = raw_tree
             .remove(&id)
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt,
     path::{Path, PathBuf},
     sync::Arc,
@@ -106,6 +107,16 @@ pub struct InstanceContext {
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub transformer_rules: Arc<Vec<TransformerRule>>,
+
+    /// Variables given on the command line, which override variables of the
+    /// same name in any project file.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: Arc<BTreeMap<String, String>>,
+
+    /// The profile selected on the command line, which is applied to any
+    /// project file that defines it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Arc<str>>,
 }
 
 impl InstanceContext {
@@ -145,6 +156,17 @@ impl InstanceContext {
         rules.extend(new_rules);
     }
 
+    /// Sets the variables that override variables of the same name in any
+    /// project file.
+    pub fn set_variables(&mut self, variables: BTreeMap<String, String>) {
+        self.variables = Arc::new(variables);
+    }
+
+    /// Sets the profile that's applied to any project file that defines it.
+    pub fn set_profile(&mut self, profile: Option<&str>) {
+        self.profile = profile.map(Arc::from);
+    }
+
     pub fn get_transformer_override(&self, path: &Path) -> Option<Transformer> {
         for rule in self.transformer_rules.iter() {
             if rule.applies_to(path) {
@@ -161,6 +183,8 @@ impl Default for InstanceContext {
         InstanceContext {
             path_ignore_rules: Arc::new(Vec::new()),
             transformer_rules: Arc::new(Vec::new()),
+            variables: Arc::new(BTreeMap::new()),
+            profile: None,
         }
     }
 }
@@ -181,6 +205,10 @@ impl PathIgnoreRule {
     pub fn passes<P: AsRef<Path>>(&self, path: P) -> bool {
         let path = path.as_ref();
 
+        if self.glob.is_absolute() {
+            return !self.glob.is_match(path);
+        }
+
         match path.strip_prefix(&self.base_path) {
             Ok(suffix) => !self.glob.is_match(suffix),
             Err(_) => true,
@@ -249,6 +277,10 @@ impl TransformerRule {
     pub fn applies_to<P: AsRef<Path>>(&self, path: P) -> bool {
         let path = path.as_ref();
 
+        if self.pattern.is_absolute() {
+            return self.pattern.is_match(path);
+        }
+
         match path.strip_prefix(&self.base_path) {
             Ok(suffix) => self.pattern.is_match(suffix),
             Err(_) => false,
@@ -267,6 +299,17 @@ pub enum InstigatingSource {
     ),
 }
 
+impl InstigatingSource {
+    /// Returns the file this source came from. For project nodes, this is the
+    /// project file that contains the node.
+    pub fn path(&self) -> &Path {
+        match self {
+            InstigatingSource::Path(path) => path,
+            InstigatingSource::ProjectNode(path, _, _, _) => path,
+        }
+    }
+}
+
 impl fmt::Debug for InstigatingSource {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
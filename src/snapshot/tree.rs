@@ -153,6 +153,55 @@ impl RojoTree {
         RojoDescendants { queue, tree: self }
     }
 
+    /// Returns the dotted path to the given instance, like Roblox's
+    /// `Instance:GetFullName`. A DataModel at the root of the tree is left out
+    /// of the path, so services are named by themselves.
+    pub fn get_full_name(&self, id: Ref) -> String {
+        let root_id = self.get_root_id();
+        let mut names = Vec::new();
+        let mut current_id = id;
+
+        while let Some(instance) = self.inner.get_by_ref(current_id) {
+            if current_id == root_id && instance.class == "DataModel" && !names.is_empty() {
+                break;
+            }
+
+            names.push(instance.name.as_str());
+            current_id = instance.parent();
+        }
+
+        names.reverse();
+        names.join(".")
+    }
+
+    /// Finds an instance from a dotted path like the ones returned by
+    /// `get_full_name`. Instances with a period in their name can't be found
+    /// this way.
+    pub fn find_by_full_name(&self, full_name: &str) -> Option<Ref> {
+        let root_id = self.get_root_id();
+        let root = self.inner.get_by_ref(root_id)?;
+        let mut names = full_name.split('.');
+
+        // Full names start with the root's name, unless it's a DataModel.
+        if root.class != "DataModel" && names.next()? != root.name {
+            return None;
+        }
+
+        let mut current_id = root_id;
+
+        for name in names {
+            let current = self.inner.get_by_ref(current_id)?;
+
+            current_id = current.children().iter().copied().find(|&child_id| {
+                self.inner
+                    .get_by_ref(child_id)
+                    .map_or(false, |child| child.name == name)
+            })?;
+        }
+
+        Some(current_id)
+    }
+
     pub fn get_ids_at_path(&self, path: &Path) -> &[Ref] {
         self.path_to_ids.get(path)
     }
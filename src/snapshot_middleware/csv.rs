@@ -1,15 +1,18 @@
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
 
 use anyhow::Context;
 use maplit::hashmap;
-use memofs::{IoResultExt, Vfs};
+use memofs::Vfs;
 use serde::Serialize;
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
 use super::{
     dir::{dir_meta, snapshot_dir_no_meta},
-    meta_file::AdjacentMetadata,
+    meta_file::{adjacent_meta_paths, AdjacentMetadata},
     util::PathExt,
 };
 
@@ -20,7 +23,7 @@ pub fn snapshot_csv(
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
     let name = path.file_name_trim_extension()?;
 
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+    let meta_paths = adjacent_meta_paths(path, name);
     let contents = vfs.read(path)?;
 
     let table_contents = convert_localization_csv(&contents).with_context(|| {
@@ -39,11 +42,14 @@ pub fn snapshot_csv(
         .metadata(
             InstanceMetadata::new()
                 .instigating_source(path)
-                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()]),
+                .relevant_paths(vec![
+                    path.to_path_buf(),
+                    meta_paths[0].clone(),
+                    meta_paths[1].clone(),
+                ]),
         );
 
-    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
-        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+    if let Some(mut metadata) = AdjacentMetadata::read(vfs, &meta_paths)? {
         metadata.apply_all(&mut snapshot)?;
     }
 
@@ -164,6 +170,59 @@ fn convert_localization_csv(contents: &[u8]) -> Result<String, csv::Error> {
     Ok(encoded)
 }
 
+/// The inverse of `convert_localization_csv`: turns the JSON contents of a
+/// LocalizationTable back into a CSV file that Rojo can read.
+///
+/// Locale columns are written in sorted order after the well-known columns so
+/// that the output is stable.
+pub fn convert_localization_json(contents: &str) -> anyhow::Result<Vec<u8>> {
+    let entries: Vec<serde_json::Map<String, serde_json::Value>> =
+        serde_json::from_str(contents).context("LocalizationTable contents were not valid JSON")?;
+
+    let mut locales = BTreeSet::new();
+    for entry in &entries {
+        if let Some(serde_json::Value::Object(values)) = entry.get("values") {
+            locales.extend(values.keys().map(|key| key.as_str()));
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let mut headers = vec!["Key", "Source", "Context", "Example"];
+    headers.extend(locales.iter().copied());
+    writer.write_record(&headers)?;
+
+    for entry in &entries {
+        let field = |name: &str| {
+            entry
+                .get(name)
+                .and_then(|value| value.as_str())
+                .unwrap_or("")
+        };
+        let values = entry.get("values").and_then(|value| value.as_object());
+
+        let mut record = vec![
+            field("key"),
+            field("source"),
+            field("context"),
+            field("example"),
+        ];
+
+        for locale in &locales {
+            let value = values
+                .and_then(|values| values.get(*locale))
+                .and_then(|value| value.as_str())
+                .unwrap_or("");
+
+            record.push(value);
+        }
+
+        writer.write_record(&record)?;
+    }
+
+    writer.into_inner().map_err(|err| err.into_error().into())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -220,4 +279,14 @@ Ack,Ack!,,An exclamation of despair,¡Ay!"#,
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn csv_round_trip() {
+        let csv = "Key,Source,Context,Example,es\nAck,Ack!,,An exclamation of despair,¡Ay!\n";
+
+        let json = convert_localization_csv(csv.as_bytes()).unwrap();
+        let round_tripped = convert_localization_json(&json).unwrap();
+
+        assert_eq!(String::from_utf8(round_tripped).unwrap(), csv);
+    }
 }
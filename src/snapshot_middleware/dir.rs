@@ -1,10 +1,13 @@
 use std::path::Path;
 
-use memofs::{DirEntry, IoResultExt, Vfs};
+use memofs::{DirEntry, Vfs};
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
-use super::{meta_file::DirectoryMetadata, snapshot_from_vfs};
+use super::{
+    meta_file::{dir_meta_paths, DirectoryMetadata},
+    snapshot_from_vfs,
+};
 
 pub fn snapshot_dir(
     context: &InstanceContext,
@@ -26,14 +29,7 @@ pub fn snapshot_dir(
 /// Retrieves the meta file that should be applied for this directory, if it
 /// exists.
 pub fn dir_meta(vfs: &Vfs, path: &Path) -> anyhow::Result<Option<DirectoryMetadata>> {
-    let meta_path = path.join("init.meta.json");
-
-    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
-        let metadata = DirectoryMetadata::from_slice(&meta_contents, meta_path)?;
-        Ok(Some(metadata))
-    } else {
-        Ok(None)
-    }
+    DirectoryMetadata::read(vfs, &dir_meta_paths(path))
 }
 
 /// Snapshot a directory without applying meta files; useful for if the
@@ -73,11 +69,12 @@ pub fn snapshot_dir_no_meta(
         .ok_or_else(|| anyhow::anyhow!("File name was not valid UTF-8: {}", path.display()))?
         .to_string();
 
-    let meta_path = path.join("init.meta.json");
+    let [meta_path, toml_meta_path] = dir_meta_paths(path);
 
     let relevant_paths = vec![
         path.to_path_buf(),
-        meta_path.clone(),
+        meta_path,
+        toml_meta_path,
         // TODO: We shouldn't need to know about Lua existing in this
         // middleware. Should we figure out a way for that function to add
         // relevant paths to this middleware?
@@ -2,14 +2,17 @@ use std::path::Path;
 
 use anyhow::Context;
 use maplit::hashmap;
-use memofs::{IoResultExt, Vfs};
+use memofs::Vfs;
 
 use crate::{
     lua_ast::{Expression, Statement},
     snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
 };
 
-use super::{meta_file::AdjacentMetadata, util::PathExt};
+use super::{
+    meta_file::{adjacent_meta_paths, AdjacentMetadata},
+    util::PathExt,
+};
 
 pub fn snapshot_json(
     context: &InstanceContext,
@@ -28,7 +31,7 @@ pub fn snapshot_json(
         "Source".to_owned() => as_lua.into(),
     };
 
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+    let meta_paths = adjacent_meta_paths(path, name);
 
     let mut snapshot = InstanceSnapshot::new()
         .name(name)
@@ -37,12 +40,15 @@ pub fn snapshot_json(
         .metadata(
             InstanceMetadata::new()
                 .instigating_source(path)
-                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .relevant_paths(vec![
+                    path.to_path_buf(),
+                    meta_paths[0].clone(),
+                    meta_paths[1].clone(),
+                ])
                 .context(context),
         );
 
-    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
-        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+    if let Some(mut metadata) = AdjacentMetadata::read(vfs, &meta_paths)? {
         metadata.apply_all(&mut snapshot)?;
     }
 
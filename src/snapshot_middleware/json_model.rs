@@ -1,12 +1,22 @@
-use std::{borrow::Cow, collections::HashMap, path::Path, str};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    str,
+};
 
 use anyhow::Context;
 use memofs::Vfs;
-use rbx_dom_weak::types::Attributes;
-use serde::Deserialize;
+use rbx_dom_weak::{
+    types::{Attributes, Ref, Variant},
+    Instance, WeakDom,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    resolution::UnresolvedValue,
+    jsonc,
+    resolution::{should_persist_property, UnresolvedValue},
     snapshot::{InstanceContext, InstanceSnapshot},
 };
 
@@ -31,7 +41,7 @@ pub fn snapshot_json_model(
         return Ok(None);
     }
 
-    let mut instance: JsonModel = serde_json::from_str(contents_str)
+    let mut instance: JsonModel = jsonc::from_slice(contents_str.as_bytes())
         .with_context(|| format!("File is not a valid JSON model: {}", path.display()))?;
 
     if let Some(top_level_name) = &instance.name {
@@ -61,34 +71,67 @@ pub fn snapshot_json_model(
     Ok(Some(snapshot))
 }
 
-#[derive(Debug, Deserialize)]
+/// The format of `.model.json` files.
+///
+/// Models can be read with `snapshot_json_model` and written back out by
+/// serializing a model created with `JsonModel::from_instance`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
-struct JsonModel {
-    #[serde(alias = "Name")]
-    name: Option<String>,
+pub struct JsonModel {
+    #[serde(alias = "Name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 
     #[serde(alias = "ClassName")]
-    class_name: String,
+    pub class_name: String,
 
     #[serde(
-        alias = "Children",
-        default = "Vec::new",
-        skip_serializing_if = "Vec::is_empty"
+        alias = "Properties",
+        default = "BTreeMap::new",
+        skip_serializing_if = "BTreeMap::is_empty"
     )]
-    children: Vec<JsonModel>,
+    pub properties: BTreeMap<String, UnresolvedValue>,
 
     #[serde(
-        alias = "Properties",
-        default = "HashMap::new",
-        skip_serializing_if = "HashMap::is_empty"
+        alias = "Attributes",
+        default = "BTreeMap::new",
+        skip_serializing_if = "BTreeMap::is_empty"
     )]
-    properties: HashMap<String, UnresolvedValue>,
+    pub attributes: BTreeMap<String, UnresolvedValue>,
 
-    #[serde(default = "HashMap::new", skip_serializing_if = "HashMap::is_empty")]
-    attributes: HashMap<String, UnresolvedValue>,
+    #[serde(
+        alias = "Children",
+        default = "Vec::new",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub children: Vec<JsonModel>,
 }
 
 impl JsonModel {
+    /// Creates a model describing the given instance and all of its
+    /// descendants. Properties that don't need to be persisted, like ones set
+    /// to their default value, are left out.
+    ///
+    /// The top-level model is given a name; callers writing it to a file
+    /// should clear it, since the name comes from the file name instead.
+    pub fn from_instance(dom: &WeakDom, id: Ref) -> Self {
+        let instance = dom.get_by_ref(id).expect("instance did not exist");
+        let (properties, attributes) = split_properties(instance);
+
+        let children = instance
+            .children()
+            .iter()
+            .map(|&child_id| Self::from_instance(dom, child_id))
+            .collect();
+
+        Self {
+            name: Some(instance.name.clone()),
+            class_name: instance.class.clone(),
+            properties,
+            attributes,
+            children,
+        }
+    }
+
     fn into_snapshot(self) -> anyhow::Result<InstanceSnapshot> {
         let name = self.name.unwrap_or_else(|| self.class_name.clone());
         let class_name = self.class_name;
@@ -126,6 +169,42 @@ impl JsonModel {
     }
 }
 
+/// Splits the properties of an instance into the `properties` and `attributes`
+/// maps used by Rojo's JSON file formats, leaving out anything that doesn't
+/// need to be persisted.
+pub fn split_properties(
+    instance: &Instance,
+) -> (
+    BTreeMap<String, UnresolvedValue>,
+    BTreeMap<String, UnresolvedValue>,
+) {
+    let mut properties = BTreeMap::new();
+    let mut attributes = BTreeMap::new();
+
+    for (key, value) in &instance.properties {
+        match value {
+            Variant::Attributes(instance_attributes) => {
+                for (name, value) in instance_attributes.iter() {
+                    attributes.insert(
+                        name.to_owned(),
+                        UnresolvedValue::FullyQualified(value.clone()),
+                    );
+                }
+            }
+            _ => {
+                if should_persist_property(&instance.class, key, value) {
+                    properties.insert(
+                        key.to_owned(),
+                        UnresolvedValue::FullyQualified(value.clone()),
+                    );
+                }
+            }
+        }
+    }
+
+    (properties, attributes)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -205,4 +284,45 @@ mod test {
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn model_round_trip() {
+        use rbx_dom_weak::InstanceBuilder;
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("IntValue")
+                .with_name("Root")
+                .with_property("Value", 5i64)
+                .with_child(InstanceBuilder::new("StringValue").with_name("The Child")),
+        );
+
+        let mut model = JsonModel::from_instance(&dom, dom.root_ref());
+        model.name = None;
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.model.json",
+            VfsSnapshot::file(serde_json::to_string_pretty(&model).unwrap()),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_json_model(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.model.json"),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(instance_snapshot.name, "foo");
+        assert_eq!(instance_snapshot.class_name, "IntValue");
+        assert_eq!(
+            instance_snapshot.properties.get("Value"),
+            Some(&Variant::Int64(5))
+        );
+        assert_eq!(instance_snapshot.children.len(), 1);
+        assert_eq!(instance_snapshot.children[0].name, "The Child");
+    }
 }
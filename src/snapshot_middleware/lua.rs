@@ -2,13 +2,13 @@ use std::{path::Path, str};
 
 use anyhow::Context;
 use maplit::hashmap;
-use memofs::{IoResultExt, Vfs};
+use memofs::Vfs;
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
 use super::{
     dir::{dir_meta, snapshot_dir_no_meta},
-    meta_file::AdjacentMetadata,
+    meta_file::{adjacent_meta_paths, AdjacentMetadata},
     util::match_trailing,
 };
 
@@ -61,7 +61,7 @@ pub fn snapshot_lua(
         .with_context(|| format!("File was not valid UTF-8: {}", path.display()))?
         .to_owned();
 
-    let meta_path = path.with_file_name(format!("{}.meta.json", instance_name));
+    let meta_paths = adjacent_meta_paths(path, instance_name);
 
     let mut snapshot = InstanceSnapshot::new()
         .name(instance_name)
@@ -72,12 +72,15 @@ pub fn snapshot_lua(
         .metadata(
             InstanceMetadata::new()
                 .instigating_source(path)
-                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .relevant_paths(vec![
+                    path.to_path_buf(),
+                    meta_paths[0].clone(),
+                    meta_paths[1].clone(),
+                ])
                 .context(context),
         );
 
-    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
-        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+    if let Some(mut metadata) = AdjacentMetadata::read(vfs, &meta_paths)? {
         metadata.apply_all(&mut snapshot)?;
     }
 
@@ -1,16 +1,75 @@
-use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::{format_err, Context};
+use memofs::{IoResultExt, Vfs};
 use rbx_dom_weak::types::Attributes;
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{jsonc, resolution::UnresolvedValue, snapshot::InstanceSnapshot};
+
+/// Returns the paths that the meta file for an instance named `name`, made
+/// from the file at `path`, can be at. Meta files can be written in JSON or
+/// TOML, like hello.meta.json or hello.meta.toml.
+pub fn adjacent_meta_paths(path: &Path, name: &str) -> [PathBuf; 2] {
+    [
+        path.with_file_name(format!("{}.meta.json", name)),
+        path.with_file_name(format!("{}.meta.toml", name)),
+    ]
+}
+
+/// Returns the paths that the meta file for the directory at `path` can be at.
+pub fn dir_meta_paths(path: &Path) -> [PathBuf; 2] {
+    [path.join("init.meta.json"), path.join("init.meta.toml")]
+}
+
+/// Reads the first meta file that exists out of the given paths.
+fn read_meta_file(vfs: &Vfs, paths: &[PathBuf]) -> anyhow::Result<Option<(Arc<Vec<u8>>, PathBuf)>> {
+    for path in paths {
+        if let Some(contents) = vfs.read(path).with_not_found()? {
+            return Ok(Some((contents, path.clone())));
+        }
+    }
+
+    Ok(None)
+}
 
-use crate::{resolution::UnresolvedValue, snapshot::InstanceSnapshot};
+/// Deserializes a meta file as TOML or as JSON, which can contain comments
+/// and trailing commas, depending on its extension.
+fn parse_meta_file<T: DeserializeOwned>(slice: &[u8], path: &Path) -> anyhow::Result<T> {
+    let is_toml = path
+        .extension()
+        .map_or(false, |extension| extension == "toml");
+
+    let meta = if is_toml {
+        toml::from_slice(slice).with_context(|| {
+            format!(
+                "File contained malformed meta file TOML: {}",
+                path.display()
+            )
+        })?
+    } else {
+        jsonc::from_slice(slice).with_context(|| {
+            format!(
+                "File contained malformed meta file JSON: {}",
+                path.display()
+            )
+        })?
+    };
+
+    Ok(meta)
+}
 
 /// Represents metadata in a sibling file with the same basename.
 ///
 /// As an example, hello.meta.json next to hello.lua would allow assigning
 /// additional metadata to the instance resulting from hello.lua.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AdjacentMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -28,17 +87,20 @@ pub struct AdjacentMetadata {
 
 impl AdjacentMetadata {
     pub fn from_slice(slice: &[u8], path: PathBuf) -> anyhow::Result<Self> {
-        let mut meta: Self = serde_json::from_slice(slice).with_context(|| {
-            format!(
-                "File contained malformed .meta.json data: {}",
-                path.display()
-            )
-        })?;
+        let mut meta: Self = parse_meta_file(slice, &path)?;
 
         meta.path = path;
         Ok(meta)
     }
 
+    /// Reads the meta file at the first of `paths` that exists, if any do.
+    pub fn read(vfs: &Vfs, paths: &[PathBuf]) -> anyhow::Result<Option<Self>> {
+        match read_meta_file(vfs, paths)? {
+            Some((contents, path)) => Ok(Some(Self::from_slice(&contents, path)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn apply_ignore_unknown_instances(&mut self, snapshot: &mut InstanceSnapshot) {
         if let Some(ignore) = self.ignore_unknown_instances.take() {
             snapshot.metadata.ignore_unknown_instances = ignore;
@@ -86,7 +148,7 @@ impl AdjacentMetadata {
 /// folder.
 ///
 /// This is always sourced from a file named init.meta.json.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DirectoryMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -107,17 +169,20 @@ pub struct DirectoryMetadata {
 
 impl DirectoryMetadata {
     pub fn from_slice(slice: &[u8], path: PathBuf) -> anyhow::Result<Self> {
-        let mut meta: Self = serde_json::from_slice(slice).with_context(|| {
-            format!(
-                "File contained malformed init.meta.json data: {}",
-                path.display()
-            )
-        })?;
+        let mut meta: Self = parse_meta_file(slice, &path)?;
 
         meta.path = path;
         Ok(meta)
     }
 
+    /// Reads the meta file at the first of `paths` that exists, if any do.
+    pub fn read(vfs: &Vfs, paths: &[PathBuf]) -> anyhow::Result<Option<Self>> {
+        match read_meta_file(vfs, paths)? {
+            Some((contents, path)) => Ok(Some(Self::from_slice(&contents, path)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn apply_all(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
         self.apply_ignore_unknown_instances(snapshot);
         self.apply_class_name(snapshot)?;
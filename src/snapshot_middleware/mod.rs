@@ -39,15 +39,23 @@ use self::{
     util::PathExt,
 };
 
-pub use self::project::snapshot_project_node;
+pub use self::csv::convert_localization_json;
+pub use self::json_model::{split_properties, JsonModel};
+pub use self::meta_file::{dir_meta_paths, AdjacentMetadata, DirectoryMetadata};
+pub use self::project::{infer_class_name, snapshot_project_node};
 
 /// Returns the path of the first relevant `init` file in the given directory.
-fn get_init_path(vfs: &Vfs, path: &Path) -> anyhow::Result<Option<PathBuf>> {
+pub fn get_init_path(vfs: &Vfs, path: &Path) -> anyhow::Result<Option<PathBuf>> {
     let project_path = path.join("default.project.json");
     if vfs.metadata(&project_path).with_not_found()?.is_some() {
         return Ok(Some(project_path));
     }
 
+    let project_path = path.join("default.project.toml");
+    if vfs.metadata(&project_path).with_not_found()?.is_some() {
+        return Ok(Some(project_path));
+    }
+
     let init_path = path.join("init.luau");
     if vfs.metadata(&init_path).with_not_found()?.is_some() {
         return Ok(Some(init_path));
@@ -88,7 +96,7 @@ fn get_init_path(vfs: &Vfs, path: &Path) -> anyhow::Result<Option<PathBuf>> {
 
 /// Returns the transformer for the object. Any override rules in the `context`
 /// take precedence.
-fn get_transformer(context: &InstanceContext, path: &Path) -> Option<Transformer> {
+pub fn get_transformer(context: &InstanceContext, path: &Path) -> Option<Transformer> {
     if let Some(rojo_type) = context.get_transformer_override(path) {
         return Some(rojo_type);
     }
@@ -99,12 +107,13 @@ fn get_transformer(context: &InstanceContext, path: &Path) -> Option<Transformer
         Some(Transformer::LuauClient)
     } else if path.file_name_ends_with(".lua") || path.file_name_ends_with(".luau") {
         Some(Transformer::LuauModule)
-    } else if path.file_name_ends_with(".project.json") {
+    } else if path.file_name_ends_with(".project.json") || path.file_name_ends_with(".project.toml")
+    {
         Some(Transformer::Project)
     } else if path.file_name_ends_with(".model.json") {
         Some(Transformer::JsonModel)
-    } else if path.file_name_ends_with(".meta.json") {
-        // .meta.json files do not turn into their own instances.
+    } else if path.file_name_ends_with(".meta.json") || path.file_name_ends_with(".meta.toml") {
+        // Meta files do not turn into their own instances.
         None
     } else if path.file_name_ends_with(".json") {
         Some(Transformer::Json)
@@ -1,4 +1,4 @@
-use std::{borrow::Cow, collections::HashMap, path::Path};
+use std::{borrow::Cow, collections::HashMap, path::Path, sync::Arc};
 
 use anyhow::{bail, Context};
 use memofs::Vfs;
@@ -20,8 +20,13 @@ pub fn snapshot_project(
     vfs: &Vfs,
     path: &Path,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let project = Project::load_from_slice(&vfs.read(path)?, path)
-        .with_context(|| format!("File was not a valid Rojo project: {}", path.display()))?;
+    let mut project =
+        Project::load_from_slice_with_variables(&vfs.read(path)?, path, &context.variables)
+            .with_context(|| format!("File was not a valid Rojo project: {}", path.display()))?;
+
+    if let Some(profile) = &context.profile {
+        project.apply_profile(profile);
+    }
 
     let mut context = context.clone();
 
@@ -64,6 +69,14 @@ pub fn snapshot_project(
             // file being updated.
             snapshot.metadata.relevant_paths.push(path.to_path_buf());
 
+            // Projects that this one extends are read outside of the VFS, so
+            // they're read through it here to watch them too. Changing one
+            // will snapshot this project file again.
+            for extended_path in &project.extended_files {
+                vfs.read(extended_path)?;
+                snapshot.metadata.relevant_paths.push(extended_path.clone());
+            }
+
             Ok(Some(snapshot))
         }
         None => Ok(None),
@@ -275,6 +288,12 @@ pub fn snapshot_project_node(
         metadata.ignore_unknown_instances = true;
     }
 
+    // Nodes are snapshotted again with this metadata's context when the
+    // project changes, so it needs to carry any variables and profile from the
+    // command line even if the node has no $path.
+    metadata.context.variables = Arc::clone(&context.variables);
+    metadata.context.profile = context.profile.clone();
+
     metadata.instigating_source = Some(InstigatingSource::ProjectNode(
         project_path.to_path_buf(),
         instance_name.to_string(),
@@ -292,7 +311,7 @@ pub fn snapshot_project_node(
     }))
 }
 
-fn infer_class_name(name: &str, parent_class: Option<&str>) -> Option<Cow<'static, str>> {
+pub fn infer_class_name(name: &str, parent_class: Option<&str>) -> Option<Cow<'static, str>> {
     // If className wasn't defined from another source, we may be able
     // to infer one.
 
@@ -0,0 +1,260 @@
+//! Collects information about a build that can be stamped into the built file
+//! as attributes, so that running code can report exactly which build it came
+//! from.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::types::{Attributes, Variant};
+
+use crate::snapshot::RojoTree;
+
+/// The name of the attribute containing when the build happened, as an RFC 3339
+/// timestamp.
+pub const BUILD_TIME_ATTRIBUTE: &str = "BuildTime";
+
+/// The name of the attribute containing the user-supplied version string.
+pub const BUILD_VERSION_ATTRIBUTE: &str = "BuildVersion";
+
+/// The name of the attribute containing the git commit that was checked out.
+pub const BUILD_COMMIT_ATTRIBUTE: &str = "BuildCommit";
+
+/// The name of the attribute containing a hash of every file that went into
+/// the build.
+pub const BUILD_CONTENT_HASH_ATTRIBUTE: &str = "BuildContentHash";
+
+const STAMP_ATTRIBUTES: [&str; 4] = [
+    BUILD_TIME_ATTRIBUTE,
+    BUILD_VERSION_ATTRIBUTE,
+    BUILD_COMMIT_ATTRIBUTE,
+    BUILD_CONTENT_HASH_ATTRIBUTE,
+];
+
+/// Describes a single build of a project.
+#[derive(Debug, Clone)]
+pub struct BuildInfo {
+    pub time: SystemTime,
+    pub version: Option<String>,
+    pub commit: Option<String>,
+    pub content_hash: blake3::Hash,
+}
+
+impl BuildInfo {
+    /// Collects information about building the given tree right now.
+    pub fn collect(
+        tree: &RojoTree,
+        vfs: &Vfs,
+        root_dir: &Path,
+        version: Option<String>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            time: SystemTime::now(),
+            version,
+            commit: find_git_commit(root_dir),
+            content_hash: hash_relevant_paths(tree, vfs, root_dir)?,
+        })
+    }
+
+    /// Adds this build's attributes to the given properties of an instance,
+    /// keeping any other attributes that the instance already has.
+    pub fn apply(&self, properties: &mut HashMap<String, Variant>) {
+        let mut attributes = match properties.remove("Attributes") {
+            Some(Variant::Attributes(attributes)) => attributes,
+            _ => Attributes::new(),
+        };
+
+        attributes.insert(
+            BUILD_TIME_ATTRIBUTE.to_owned(),
+            Variant::String(humantime::format_rfc3339_seconds(self.time).to_string()),
+        );
+
+        if let Some(version) = &self.version {
+            attributes.insert(
+                BUILD_VERSION_ATTRIBUTE.to_owned(),
+                Variant::String(version.clone()),
+            );
+        }
+
+        if let Some(commit) = &self.commit {
+            attributes.insert(
+                BUILD_COMMIT_ATTRIBUTE.to_owned(),
+                Variant::String(commit.clone()),
+            );
+        }
+
+        attributes.insert(
+            BUILD_CONTENT_HASH_ATTRIBUTE.to_owned(),
+            Variant::String(self.content_hash.to_hex().to_string()),
+        );
+
+        properties.insert("Attributes".to_owned(), attributes.into());
+    }
+}
+
+/// Removes any attributes added by `BuildInfo::apply` from the given properties
+/// of an instance.
+pub fn remove_stamp(properties: &mut HashMap<String, Variant>) {
+    if let Some(Variant::Attributes(attributes)) = properties.get_mut("Attributes") {
+        for name in STAMP_ATTRIBUTES {
+            attributes.remove(name);
+        }
+
+        if attributes.iter().next().is_none() {
+            properties.remove("Attributes");
+        }
+    }
+}
+
+/// Hashes the contents of every file that any instance in the tree came from.
+///
+/// Files are hashed in order of their path relative to `root_dir`, so the hash
+/// doesn't depend on where the project is checked out.
+pub fn hash_relevant_paths(
+    tree: &RojoTree,
+    vfs: &Vfs,
+    root_dir: &Path,
+) -> anyhow::Result<blake3::Hash> {
+    let paths: BTreeSet<&PathBuf> = tree
+        .descendants(tree.get_root_id())
+        .flat_map(|instance| instance.metadata().relevant_paths.iter())
+        .collect();
+
+    let mut hasher = blake3::Hasher::new();
+
+    for path in paths {
+        // Relevant paths include files that might exist but don't, as well as
+        // directories. Only files that exist have contents worth hashing.
+        match vfs.metadata(path).with_not_found()? {
+            Some(meta) if meta.is_file() => {}
+            _ => continue,
+        }
+
+        let contents = vfs.read(path)?;
+        let relative_path = path.strip_prefix(root_dir).unwrap_or(path);
+
+        // Paths are hashed with forward slashes so that the hash is the same
+        // on every platform.
+        let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+
+        hasher.update(relative_path.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&(contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+
+    Ok(hasher.finalize())
+}
+
+/// Finds the commit that's checked out in the git repository containing
+/// `start_dir` by reading the `.git` directory directly.
+pub fn find_git_commit(start_dir: &Path) -> Option<String> {
+    let dot_git = start_dir
+        .ancestors()
+        .map(|dir| dir.join(".git"))
+        .find(|path| path.exists())?;
+
+    // Worktrees and submodules have a .git file pointing to the real git
+    // directory instead of a .git directory.
+    let git_dir = if dot_git.is_file() {
+        let contents = fs::read_to_string(&dot_git).ok()?;
+        let target = contents.trim().strip_prefix("gitdir:")?.trim();
+
+        dot_git.parent()?.join(target)
+    } else {
+        dot_git
+    };
+
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    let reference = match head.strip_prefix("ref:") {
+        Some(reference) => reference.trim(),
+
+        // A detached HEAD contains the commit itself.
+        None => return Some(head.to_owned()),
+    };
+
+    // Worktrees keep branches in the main repository's git directory.
+    let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.clone(),
+    };
+
+    for dir in [&git_dir, &common_dir] {
+        if let Ok(commit) = fs::read_to_string(dir.join(reference)) {
+            return Some(commit.trim().to_owned());
+        }
+    }
+
+    let packed_refs = fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+
+    packed_refs
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .find_map(|line| {
+            let (commit, name) = line.split_once(' ')?;
+
+            if name == reference {
+                Some(commit.to_owned())
+            } else {
+                None
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COMMIT: &str = "8c1e6c4a2b9dd6f3c3e2f0a1b4c5d6e7f8091a2b";
+
+    #[test]
+    fn git_commit_from_loose_ref() {
+        let dir = tempfile::tempdir().unwrap();
+        let git_dir = dir.path().join(".git");
+
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(git_dir.join("refs/heads/main"), format!("{}\n", COMMIT)).unwrap();
+
+        let project_dir = dir.path().join("project");
+        fs::create_dir(&project_dir).unwrap();
+
+        assert_eq!(find_git_commit(&project_dir).as_deref(), Some(COMMIT));
+    }
+
+    #[test]
+    fn git_commit_from_packed_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let git_dir = dir.path().join(".git");
+
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(
+            git_dir.join("packed-refs"),
+            format!(
+                "# pack-refs with: peeled fully-peeled sorted\n{} refs/heads/main\n",
+                COMMIT
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(find_git_commit(dir.path()).as_deref(), Some(COMMIT));
+    }
+
+    #[test]
+    fn git_commit_detached() {
+        let dir = tempfile::tempdir().unwrap();
+        let git_dir = dir.path().join(".git");
+
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::write(git_dir.join("HEAD"), format!("{}\n", COMMIT)).unwrap();
+
+        assert_eq!(find_git_commit(dir.path()).as_deref(), Some(COMMIT));
+    }
+}
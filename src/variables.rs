@@ -0,0 +1,139 @@
+//! Substitutes variables like `${PLACE_ID}` into strings from project files.
+
+use std::borrow::Cow;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum VariableError {
+    #[error(
+        "Variable {name} is not set. Set it with --var {name}=<value>, in the project's \
+         variables, or as an environment variable, or give it a default like ${{{name}:-default}}"
+    )]
+    Unset { name: String },
+
+    #[error("'{name}' is not a valid variable name")]
+    InvalidName { name: String },
+
+    #[error("'${{' is missing a closing '}}' in '{input}'")]
+    Unclosed { input: String },
+}
+
+/// Replaces every `${NAME}` in `input` with the value `lookup` gives for
+/// `NAME`.
+///
+/// `${NAME:-default}` uses `default` if the variable is unset or empty, and
+/// `$${` is written as a literal `${`. Strings without any variables are
+/// returned as-is.
+pub fn interpolate<'a>(
+    input: &'a str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Cow<'a, str>, VariableError> {
+    if !input.contains("${") {
+        return Ok(Cow::Borrowed(input));
+    }
+
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+
+        let after_start = &rest[start + 2..];
+        let end = after_start
+            .find('}')
+            .ok_or_else(|| VariableError::Unclosed {
+                input: input.to_owned(),
+            })?;
+
+        let reference = &after_start[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+
+        if !is_valid_name(name) {
+            return Err(VariableError::InvalidName {
+                name: name.to_owned(),
+            });
+        }
+
+        let value = match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => default.to_owned(),
+            (Some(value), _) => value,
+            (None, Some(default)) => default.to_owned(),
+            (None, None) => {
+                return Err(VariableError::Unset {
+                    name: name.to_owned(),
+                })
+            }
+        };
+
+        output.push_str(&value);
+        rest = &after_start[end + 1..];
+    }
+
+    output.push_str(rest);
+
+    Ok(Cow::Owned(output))
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PLACE_ID" => Some("1234".to_owned()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        assert_eq!(interpolate("${PLACE_ID}", lookup).unwrap(), "1234");
+        assert_eq!(
+            interpolate("https://${HOST:-localhost}/places/${PLACE_ID}", lookup).unwrap(),
+            "https://localhost/places/1234"
+        );
+        assert_eq!(
+            interpolate("${EMPTY:-fallback}", lookup).unwrap(),
+            "fallback"
+        );
+        assert_eq!(interpolate("$${PLACE_ID}", lookup).unwrap(), "${PLACE_ID}");
+        assert_eq!(interpolate("$5 and $path", lookup).unwrap(), "$5 and $path");
+    }
+
+    #[test]
+    fn reports_bad_variables() {
+        assert!(matches!(
+            interpolate("${MISSING}", lookup),
+            Err(VariableError::Unset { .. })
+        ));
+        assert!(matches!(
+            interpolate("${PLACE_ID", lookup),
+            Err(VariableError::Unclosed { .. })
+        ));
+        assert!(matches!(
+            interpolate("${1NAME}", lookup),
+            Err(VariableError::InvalidName { .. })
+        ));
+    }
+}
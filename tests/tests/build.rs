@@ -1,9 +1,12 @@
-use std::{fs, path::Path, process::Command};
+use std::{collections::HashMap, fs, io::BufReader, path::Path, process::Command};
 
 use insta::assert_snapshot;
+use rbx_dom_weak::{types::Variant, WeakDom};
 use tempfile::tempdir;
 
-use crate::rojo_test::io_util::{get_working_dir_path, BUILD_TESTS_PATH, ROJO_PATH};
+use crate::rojo_test::io_util::{
+    copy_recursive, get_working_dir_path, BUILD_TESTS_PATH, ROJO_PATH,
+};
 
 macro_rules! gen_build_tests {
     ( $($test_name: ident,)* ) => {
@@ -62,22 +65,348 @@ gen_build_tests! {
 }
 
 fn run_build_test(test_name: &str) {
-    let working_dir = get_working_dir_path();
-
-    let input_path = Path::new(BUILD_TESTS_PATH).join(test_name);
+    run_build_test_with_args(test_name, test_name, &[]);
+}
 
+/// Builds one of the build test projects into an XML model with extra
+/// arguments, and compares it with the snapshot named `snapshot_name`.
+fn run_build_test_with_args(test_name: &str, snapshot_name: &str, args: &[&str]) {
     let output_dir = tempdir().expect("couldn't create temporary directory");
     let output_path = output_dir.path().join(format!("{}.rbxmx", test_name));
 
+    run_rojo_build(test_name, &output_path, args);
+
+    let contents = fs::read_to_string(&output_path).expect("Couldn't read output file");
+
+    assert_build_snapshot(snapshot_name, &contents);
+}
+
+fn assert_build_snapshot(snapshot_name: &str, contents: &str) {
+    let mut settings = insta::Settings::new();
+
+    let snapshot_path = Path::new(BUILD_TESTS_PATH)
+        .parent()
+        .unwrap()
+        .join("build-test-snapshots");
+
+    settings.set_snapshot_path(snapshot_path);
+
+    settings.bind(|| {
+        assert_snapshot!(snapshot_name, contents);
+    });
+}
+
+#[test]
+fn build_subtree() {
+    let _ = env_logger::try_init();
+
+    run_build_test_with_args(
+        "subtree",
+        "subtree",
+        &["--subtree", "ReplicatedStorage.Shared"],
+    );
+}
+
+#[test]
+fn build_subtree_into_place() {
+    let _ = env_logger::try_init();
+
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join("subtree.rbxlx");
+
+    let output = rojo_build_command(
+        "subtree",
+        &output_path,
+        &["--subtree", "ReplicatedStorage.Shared"],
+    )
+    .output()
+    .expect("Couldn't start Rojo");
+
+    assert!(
+        !output.status.success(),
+        "Rojo built a subtree into a place"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("can only be built into a model"));
+    assert!(!output_path.exists());
+}
+
+#[test]
+fn build_base() {
+    let _ = env_logger::try_init();
+
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let base_path = output_dir.path().join("base_place.rbxlx");
+    let output_path = output_dir.path().join("base.rbxlx");
+
+    run_rojo_build("base_place", &base_path, &["--deterministic"]);
+    run_rojo_build(
+        "base",
+        &output_path,
+        &["--base", base_path.to_str().unwrap(), "--deterministic"],
+    );
+
+    // The base place's Workspace and ReplicatedStorage.Old aren't in the
+    // project, but are kept along with the Ref between the instances in Map.
+    let contents = fs::read_to_string(&output_path).expect("Couldn't read output file");
+
+    assert_build_snapshot("base", &contents);
+}
+
+#[test]
+fn build_deterministic() {
+    let _ = env_logger::try_init();
+
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+
+    for (test_name, extension) in [("rbxmx_ref", "rbxm"), ("base_place", "rbxlx")] {
+        let first_path = output_dir
+            .path()
+            .join(format!("{}-1.{}", test_name, extension));
+        let second_path = output_dir
+            .path()
+            .join(format!("{}-2.{}", test_name, extension));
+
+        run_rojo_build(test_name, &first_path, &["--deterministic"]);
+        run_rojo_build(test_name, &second_path, &["--deterministic"]);
+
+        let first = fs::read(&first_path).expect("Couldn't read output file");
+        let second = fs::read(&second_path).expect("Couldn't read output file");
+
+        assert!(
+            first == second,
+            "Building {} into a .{} file twice gave different bytes",
+            test_name,
+            extension
+        );
+    }
+
+    // Referents come from instance paths, so adding an instance leaves the
+    // referents of every other instance alone.
+    let project_path = output_dir.path().join("manifest");
+    let output_path = output_dir.path().join("manifest.rbxmx");
+
+    copy_recursive(&Path::new(BUILD_TESTS_PATH).join("manifest"), &project_path)
+        .expect("Couldn't copy test project");
+
+    let build = || {
+        let output = Command::new(ROJO_PATH)
+            .args(&[
+                "build",
+                project_path.to_str().unwrap(),
+                "-o",
+                output_path.to_str().unwrap(),
+                "--deterministic",
+            ])
+            .env("RUST_LOG", "error")
+            .output()
+            .expect("Couldn't start Rojo");
+
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        assert!(output.status.success(), "Rojo did not exit successfully");
+
+        referents_by_name(&fs::read_to_string(&output_path).expect("Couldn't read output file"))
+    };
+
+    let before = build();
+
+    // Sorts before Greeting, so it's written before most of the tree.
+    fs::write(project_path.join("shared").join("Added.txt"), "New!").unwrap();
+    let after = build();
+
+    assert_eq!(after.len(), before.len() + 1);
+    assert!(after.contains_key("Added"));
+
+    for (name, referent) in &before {
+        assert_eq!(&after[name], referent, "The referent of {} changed", name);
+    }
+}
+
+/// Reads the referent of every item in an XML file written by Rojo, keyed by
+/// the item's name.
+fn referents_by_name(contents: &str) -> HashMap<String, String> {
+    let mut referents = HashMap::new();
+    let mut referent = None;
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("<Item ") {
+            let start = rest.find("referent=\"").unwrap() + "referent=\"".len();
+            let end = start + rest[start..].find('"').unwrap();
+            referent = Some(rest[start..end].to_owned());
+        } else if let Some(rest) = line.strip_prefix("<string name=\"Name\">") {
+            let name = rest.strip_suffix("</string>").unwrap();
+            referents.insert(name.to_owned(), referent.take().unwrap());
+        }
+    }
+
+    referents
+}
+
+#[test]
+fn build_incremental() {
+    let _ = env_logger::try_init();
+
+    // The project is copied so that the test can change one of its files.
+    let dir = tempdir().expect("couldn't create temporary directory");
+    let project_path = dir.path().join("txt");
+    let output_path = dir.path().join("txt.rbxmx");
+
+    copy_recursive(&Path::new(BUILD_TESTS_PATH).join("txt"), &project_path)
+        .expect("Couldn't copy test project");
+
+    let build = || {
+        let output = Command::new(ROJO_PATH)
+            .args(&[
+                "build",
+                project_path.to_str().unwrap(),
+                "-o",
+                output_path.to_str().unwrap(),
+                "--incremental",
+            ])
+            .env("RUST_LOG", "error")
+            .output()
+            .expect("Couldn't start Rojo");
+
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        assert!(output.status.success(), "Rojo did not exit successfully");
+
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert!(build().contains("Built project"));
+    assert!(dir.path().join("txt.rbxmx.rojo-fingerprint").is_file());
+
+    assert!(build().contains("is up to date"));
+
+    fs::write(project_path.join("foo.txt"), "Changed since the last build")
+        .expect("Couldn't change test project");
+
+    assert!(build().contains("Built project"));
+
+    let output = fs::read_to_string(&output_path).expect("Couldn't read output file");
+    assert!(output.contains("Changed since the last build"));
+}
+
+#[test]
+fn build_manifest() {
+    let _ = env_logger::try_init();
+
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join("manifest.rbxmx");
+    let manifest_path = output_dir.path().join("manifest.json");
+
+    run_rojo_build(
+        "manifest",
+        &output_path,
+        &["--manifest", manifest_path.to_str().unwrap()],
+    );
+
+    assert_manifest_snapshot("manifest", &manifest_path);
+}
+
+#[test]
+fn build_manifest_base() {
+    let _ = env_logger::try_init();
+
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let base_path = output_dir.path().join("base_place.rbxlx");
+    let output_path = output_dir.path().join("base.rbxlx");
+    let manifest_path = output_dir.path().join("manifest.json");
+
+    run_rojo_build("base_place", &base_path, &["--deterministic"]);
+    run_rojo_build(
+        "base",
+        &output_path,
+        &[
+            "--base",
+            base_path.to_str().unwrap(),
+            "--manifest",
+            manifest_path.to_str().unwrap(),
+        ],
+    );
+
+    // Instances kept from the base place are listed too, without any sources.
+    assert_manifest_snapshot("manifest_base", &manifest_path);
+}
+
+fn assert_manifest_snapshot(snapshot_name: &str, manifest_path: &Path) {
+    let contents = fs::read_to_string(manifest_path).expect("Couldn't read manifest");
+    let mut manifest: serde_json::Value =
+        serde_json::from_str(&contents).expect("Manifest was not valid JSON");
+
+    // The Rojo version, the output's absolute path, and file hashes change
+    // independently of the project, so they're redacted.
+    manifest["rojoVersion"] = "[version]".into();
+    manifest["outputs"][0]["path"] = "[output]".into();
+
+    for hash in manifest["files"].as_object_mut().unwrap().values_mut() {
+        assert_eq!(hash.as_str().map(str::len), Some(64));
+        *hash = "[hash]".into();
+    }
+
+    assert_build_snapshot(
+        snapshot_name,
+        &serde_json::to_string_pretty(&manifest).unwrap(),
+    );
+}
+
+#[test]
+fn build_stamp_place() {
+    let _ = env_logger::try_init();
+
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join("stamp_place.rbxlx");
+
+    run_rojo_build("stamp_place", &output_path, &["--stamp-version", "1.2.3"]);
+
+    // Place files don't contain the DataModel, so the stamp goes on
+    // ReplicatedStorage instead.
+    let dom = read_xml(&output_path);
+    let replicated_storage = dom
+        .root()
+        .children()
+        .iter()
+        .map(|&id| dom.get_by_ref(id).unwrap())
+        .find(|instance| instance.class == "ReplicatedStorage")
+        .expect("place had no ReplicatedStorage");
+
+    let attributes = match replicated_storage.properties.get("Attributes") {
+        Some(Variant::Attributes(attributes)) => attributes,
+        _ => panic!("ReplicatedStorage was not stamped"),
+    };
+
+    let version = attributes
+        .iter()
+        .find(|(name, _)| name.as_str() == "BuildVersion")
+        .map(|(_, value)| value);
+
+    assert_eq!(version, Some(&Variant::String("1.2.3".to_owned())));
+    assert!(attributes
+        .iter()
+        .any(|(name, _)| name.as_str() == "BuildContentHash"));
+}
+
+// The hook in this project is written for a POSIX shell.
+#[cfg(not(windows))]
+#[test]
+fn build_post_build_hook_output_path() {
+    let _ = env_logger::try_init();
+
+    let input_path = Path::new(BUILD_TESTS_PATH).join("post_build_hook");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let log_path = output_dir.path().join("hook.log");
+
+    // The output path is relative to where Rojo runs, which isn't where the
+    // hook runs.
     let output = Command::new(ROJO_PATH)
         .args(&[
             "build",
             input_path.to_str().unwrap(),
             "-o",
-            output_path.to_str().unwrap(),
+            "post_build_hook.rbxmx",
         ])
         .env("RUST_LOG", "error")
-        .current_dir(working_dir)
+        .env("ROJO_TEST_HOOK_LOG", &log_path)
+        .current_dir(output_dir.path())
         .output()
         .expect("Couldn't start Rojo");
 
@@ -86,18 +415,55 @@ fn run_build_test(test_name: &str) {
 
     assert!(output.status.success(), "Rojo did not exit successfully");
 
-    let contents = fs::read_to_string(&output_path).expect("Couldn't read output file");
+    let log = fs::read_to_string(&log_path).expect("The post-build hook did not run");
+    let mut lines = log.lines();
 
-    let mut settings = insta::Settings::new();
+    let hook_output_path = Path::new(lines.next().unwrap());
+    assert!(hook_output_path.is_absolute());
+    assert_eq!(
+        hook_output_path.canonicalize().unwrap(),
+        output_dir
+            .path()
+            .join("post_build_hook.rbxmx")
+            .canonicalize()
+            .unwrap()
+    );
+    assert_eq!(lines.next(), Some("rbxmx"));
+}
 
-    let snapshot_path = Path::new(BUILD_TESTS_PATH)
-        .parent()
-        .unwrap()
-        .join("build-test-snapshots");
+/// Runs `rojo build` on one of the build test projects with extra arguments,
+/// failing the test if the build fails.
+fn run_rojo_build(test_name: &str, output_path: &Path, args: &[&str]) {
+    let output = rojo_build_command(test_name, output_path, args)
+        .output()
+        .expect("Couldn't start Rojo");
 
-    settings.set_snapshot_path(snapshot_path);
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
 
-    settings.bind(|| {
-        assert_snapshot!(test_name, contents);
-    });
+    assert!(output.status.success(), "Rojo did not exit successfully");
+}
+
+fn rojo_build_command(test_name: &str, output_path: &Path, args: &[&str]) -> Command {
+    let input_path = Path::new(BUILD_TESTS_PATH).join(test_name);
+
+    let mut command = Command::new(ROJO_PATH);
+    command
+        .args(&[
+            "build",
+            input_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ])
+        .args(args)
+        .env("RUST_LOG", "error")
+        .current_dir(get_working_dir_path());
+
+    command
+}
+
+fn read_xml(path: &Path) -> WeakDom {
+    let file = BufReader::new(fs::File::open(path).expect("Couldn't open output file"));
+
+    rbx_xml::from_reader_default(file).expect("Couldn't decode output file")
 }
@@ -1,2 +1,4 @@
 mod build;
 mod serve;
+mod sourcemap;
+mod syncback;
@@ -0,0 +1,50 @@
+use std::{path::Path, process::Command};
+
+use tempfile::tempdir;
+
+use crate::rojo_test::io_util::{BUILD_TESTS_PATH, ROJO_PATH};
+
+// The hook in this project is written for a POSIX shell.
+#[cfg(not(windows))]
+#[test]
+fn sourcemap_does_not_run_hooks() {
+    let _ = env_logger::try_init();
+
+    let input_path = Path::new(BUILD_TESTS_PATH).join("pre_build_hook");
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let log_path = output_dir.path().join("hook.log");
+
+    let run_rojo = |args: &[&str]| {
+        let output = Command::new(ROJO_PATH)
+            .args(args)
+            .env("RUST_LOG", "error")
+            .env("ROJO_TEST_HOOK_LOG", &log_path)
+            .current_dir(output_dir.path())
+            .output()
+            .expect("Couldn't start Rojo");
+
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+        assert!(output.status.success(), "Rojo did not exit successfully");
+    };
+
+    run_rojo(&["sourcemap", input_path.to_str().unwrap()]);
+    assert!(
+        !log_path.exists(),
+        "rojo sourcemap ran the project's pre-build hook"
+    );
+
+    // Building the same project does run the hook, so the check above isn't
+    // passing just because the hook is broken.
+    run_rojo(&[
+        "build",
+        input_path.to_str().unwrap(),
+        "-o",
+        "pre_build_hook.rbxmx",
+    ]);
+    assert!(
+        log_path.exists(),
+        "rojo build did not run the pre-build hook"
+    );
+}
@@ -0,0 +1,71 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::tempdir;
+
+use crate::rojo_test::io_util::{get_working_dir_path, ROJO_PATH, SYNCBACK_TESTS_PATH};
+
+#[test]
+fn syncback_round_trip() {
+    let _ = env_logger::try_init();
+
+    run_round_trip_test("round_trip");
+}
+
+/// Builds a project, syncs the result back into an empty copy of the project,
+/// and checks that building the copy produces the same place.
+fn run_round_trip_test(test_name: &str) {
+    let input_path = Path::new(SYNCBACK_TESTS_PATH).join(test_name);
+    let temp_dir = tempdir().expect("couldn't create temporary directory");
+
+    let original_path = temp_dir.path().join("original.rbxlx");
+    run_rojo(&[
+        "build",
+        input_path.to_str().unwrap(),
+        "--deterministic",
+        "-o",
+        original_path.to_str().unwrap(),
+    ]);
+
+    let synced_dir = temp_dir.path().join("synced");
+    fs::create_dir(&synced_dir).unwrap();
+    fs::copy(
+        input_path.join("default.project.json"),
+        synced_dir.join("default.project.json"),
+    )
+    .unwrap();
+
+    run_rojo(&[
+        "syncback",
+        synced_dir.to_str().unwrap(),
+        "--input",
+        original_path.to_str().unwrap(),
+    ]);
+
+    let synced_path = temp_dir.path().join("synced.rbxlx");
+    run_rojo(&[
+        "build",
+        synced_dir.to_str().unwrap(),
+        "--deterministic",
+        "-o",
+        synced_path.to_str().unwrap(),
+    ]);
+
+    let original = fs::read_to_string(&original_path).expect("Couldn't read original place");
+    let synced = fs::read_to_string(&synced_path).expect("Couldn't read synced place");
+
+    assert_eq!(original, synced);
+}
+
+fn run_rojo(args: &[&str]) {
+    let output = Command::new(ROJO_PATH)
+        .args(args)
+        .env("RUST_LOG", "error")
+        .current_dir(get_working_dir_path())
+        .output()
+        .expect("Couldn't start Rojo");
+
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+    assert!(output.status.success(), "Rojo did not exit successfully");
+}